@@ -0,0 +1,363 @@
+use crate::{Pos, Tap};
+use std::{
+    fmt::Display,
+    hash::{Hash, Hasher},
+};
+
+const WORD: usize = u64::BITS as usize;
+
+/// Set of small integers, one bit each. Grows on insert
+#[derive(Debug, Default, Clone)]
+pub struct BitSet {
+    words: Vec<u64>,
+}
+impl BitSet {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Room for `0..bits` without reallocating
+    pub fn with_capacity(bits: usize) -> Self {
+        Self {
+            words: vec![0; bits.div_ceil(WORD)],
+        }
+    }
+
+    pub fn contains(&self, i: usize) -> bool {
+        self.words
+            .get(i / WORD)
+            .is_some_and(|w| w >> (i % WORD) & 1 == 1)
+    }
+
+    /// Returns whether `i` was newly added
+    pub fn insert(&mut self, i: usize) -> bool {
+        if i / WORD >= self.words.len() {
+            self.words.resize(i / WORD + 1, 0);
+        }
+
+        let word = &mut self.words[i / WORD];
+        let bit = 1 << (i % WORD);
+        let added = *word & bit == 0;
+        *word |= bit;
+        added
+    }
+
+    /// Returns whether `i` was present
+    pub fn remove(&mut self, i: usize) -> bool {
+        let Some(word) = self.words.get_mut(i / WORD) else {
+            return false;
+        };
+
+        let bit = 1 << (i % WORD);
+        let present = *word & bit != 0;
+        *word &= !bit;
+        present
+    }
+
+    /// Number of elements
+    pub fn len(&self) -> usize {
+        self.words.iter().map(|w| w.count_ones() as usize).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.words.iter().all(|w| *w == 0)
+    }
+
+    pub fn clear(&mut self) {
+        self.words.fill(0);
+    }
+
+    /// Elements in ascending order
+    pub fn iter(&self) -> impl Iterator<Item = usize> + '_ {
+        self.words.iter().enumerate().flat_map(|(i, &word)| {
+            let mut word = word;
+            std::iter::from_fn(move || {
+                (word != 0).then(|| {
+                    let bit = word.trailing_zeros() as usize;
+                    word &= word - 1;
+                    i * WORD + bit
+                })
+            })
+        })
+    }
+
+    pub fn union_with(&mut self, other: &Self) {
+        if other.words.len() > self.words.len() {
+            self.words.resize(other.words.len(), 0);
+        }
+        for (a, b) in self.words.iter_mut().zip(&other.words) {
+            *a |= b;
+        }
+    }
+
+    pub fn intersect_with(&mut self, other: &Self) {
+        for (i, a) in self.words.iter_mut().enumerate() {
+            *a &= other.words.get(i).copied().unwrap_or(0);
+        }
+    }
+
+    pub fn difference_with(&mut self, other: &Self) {
+        for (a, b) in self.words.iter_mut().zip(&other.words) {
+            *a &= !b;
+        }
+    }
+
+    pub fn is_disjoint(&self, other: &Self) -> bool {
+        self.words.iter().zip(&other.words).all(|(a, b)| a & b == 0)
+    }
+
+    pub fn is_subset(&self, other: &Self) -> bool {
+        self.words
+            .iter()
+            .enumerate()
+            .all(|(i, a)| a & !other.words.get(i).copied().unwrap_or(0) == 0)
+    }
+}
+impl BitSet {
+    /// Words without trailing zeros, so capacity doesn't affect equality
+    fn trimmed(&self) -> &[u64] {
+        let len = self
+            .words
+            .iter()
+            .rposition(|w| *w != 0)
+            .map_or(0, |i| i + 1);
+        &self.words[..len]
+    }
+}
+impl PartialEq for BitSet {
+    fn eq(&self, other: &Self) -> bool {
+        self.trimmed() == other.trimmed()
+    }
+}
+impl Eq for BitSet {}
+impl Hash for BitSet {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.trimmed().hash(state);
+    }
+}
+impl FromIterator<usize> for BitSet {
+    fn from_iter<T: IntoIterator<Item = usize>>(iter: T) -> Self {
+        let mut set = Self::new();
+        set.extend(iter);
+        set
+    }
+}
+impl Extend<usize> for BitSet {
+    fn extend<T: IntoIterator<Item = usize>>(&mut self, iter: T) {
+        for i in iter {
+            self.insert(i);
+        }
+    }
+}
+
+/// Set of cells in a `width` × `height` grid
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct BitGrid {
+    pub width: isize,
+    pub height: isize,
+    bits: BitSet,
+}
+impl BitGrid {
+    pub fn new(width: isize, height: isize) -> Self {
+        Self {
+            width,
+            height,
+            bits: BitSet::with_capacity((width * height) as usize),
+        }
+    }
+
+    pub fn is_inside(&self, Pos { x, y }: &Pos) -> bool {
+        (0..self.width).contains(x) && (0..self.height).contains(y)
+    }
+
+    fn index(&self, p: &Pos) -> Option<usize> {
+        self.is_inside(p).then(|| (p.y * self.width + p.x) as usize)
+    }
+
+    fn pos(&self, index: usize) -> Pos {
+        let index = index as isize;
+        Pos::new(index % self.width, index / self.width)
+    }
+
+    /// False for cells outside the grid
+    pub fn contains(&self, p: &Pos) -> bool {
+        self.index(p).is_some_and(|i| self.bits.contains(i))
+    }
+
+    /// Returns whether `p` was newly added. Panics outside the grid
+    pub fn insert(&mut self, p: Pos) -> bool {
+        let i = self
+            .index(&p)
+            .unwrap_or_else(|| panic!("{p} is outside the grid"));
+        self.bits.insert(i)
+    }
+
+    pub fn remove(&mut self, p: &Pos) -> bool {
+        self.index(p).is_some_and(|i| self.bits.remove(i))
+    }
+
+    /// Number of set cells
+    pub fn len(&self) -> usize {
+        self.bits.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.bits.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.bits.clear();
+    }
+
+    /// Set cells in row-major order
+    pub fn iter(&self) -> impl Iterator<Item = Pos> + '_ {
+        self.bits.iter().map(|i| self.pos(i))
+    }
+
+    fn assert_same_size(&self, other: &Self) {
+        assert_eq!(
+            (self.width, self.height),
+            (other.width, other.height),
+            "grids differ in size"
+        );
+    }
+
+    pub fn union_with(&mut self, other: &Self) {
+        self.assert_same_size(other);
+        self.bits.union_with(&other.bits);
+    }
+
+    pub fn intersect_with(&mut self, other: &Self) {
+        self.assert_same_size(other);
+        self.bits.intersect_with(&other.bits);
+    }
+
+    pub fn union(&self, other: &Self) -> Self {
+        self.clone().tap_mut(|x| x.union_with(other))
+    }
+
+    pub fn intersection(&self, other: &Self) -> Self {
+        self.clone().tap_mut(|x| x.intersect_with(other))
+    }
+
+    pub fn is_disjoint(&self, other: &Self) -> bool {
+        self.assert_same_size(other);
+        self.bits.is_disjoint(&other.bits)
+    }
+}
+impl Display for BitGrid {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for y in 0..self.height {
+            for x in 0..self.width {
+                let c = if self.contains(&Pos::new(x, y)) {
+                    '#'
+                } else {
+                    '.'
+                };
+                write!(f, "{c}")?;
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+/// Bit `i` set where character `i` of `row` is `on`. Rows are at most 64 wide
+pub fn row_mask(row: &str, on: char) -> u64 {
+    row.chars().enumerate().fold(0, |mask, (i, c)| {
+        assert!(i < WORD, "row is wider than {WORD}");
+        mask | ((c == on) as u64) << i
+    })
+}
+
+/// Rows of `width` cells packed into one mask, row `r` starting at bit
+/// `r * width`, or `None` if they don't fit in 64 bits
+pub fn pack_rows(rows: &[u64], width: usize) -> Option<u64> {
+    if rows.len() * width > WORD {
+        return None;
+    }
+
+    Some(
+        rows.iter()
+            .enumerate()
+            .fold(0, |mask, (r, row)| mask | row << (r * width)),
+    )
+}
+
+/// Number of rows with each of the first `width` bits set
+pub fn column_counts(rows: &[u64], width: usize) -> Vec<u32> {
+    (0..width)
+        .map(|x| rows.iter().filter(|row| *row >> x & 1 == 1).count() as u32)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bit_set() {
+        let mut set = BitSet::new();
+        assert!(set.insert(3));
+        assert!(!set.insert(3));
+        assert!(set.insert(200));
+        assert!(!set.contains(199));
+        assert_eq!(set.iter().collect::<Vec<_>>(), [3, 200]);
+        assert_eq!(set.len(), 2);
+
+        let other = BitSet::from_iter([3, 64, 65]);
+        assert!(!set.is_disjoint(&other));
+
+        let mut union = set.clone();
+        union.union_with(&other);
+        assert_eq!(union.iter().collect::<Vec<_>>(), [3, 64, 65, 200]);
+        assert!(set.is_subset(&union));
+        assert!(!union.is_subset(&set));
+
+        union.intersect_with(&other);
+        assert_eq!(union, other);
+
+        set.difference_with(&other);
+        assert_eq!(set.iter().collect::<Vec<_>>(), [200]);
+        assert!(set.remove(200));
+        assert!(set.is_empty());
+    }
+
+    #[test]
+    fn bit_grid() {
+        let mut a = BitGrid::new(4, 3);
+        assert!(a.insert(Pos::new(0, 0)));
+        assert!(a.insert(Pos::new(3, 2)));
+        assert!(!a.insert(Pos::new(3, 2)));
+        assert!(!a.contains(&Pos::new(-1, 0)));
+        assert!(!a.contains(&Pos::new(4, 0)));
+
+        let mut b = BitGrid::new(4, 3);
+        b.insert(Pos::new(3, 2));
+        b.insert(Pos::new(1, 1));
+
+        assert_eq!(
+            a.intersection(&b).iter().collect::<Vec<_>>(),
+            [Pos::new(3, 2)]
+        );
+        assert_eq!(
+            a.union(&b).iter().collect::<Vec<_>>(),
+            [Pos::new(0, 0), Pos::new(1, 1), Pos::new(3, 2)]
+        );
+        assert_eq!(a.union(&b).len(), 3);
+        assert_eq!(b.to_string(), "....\n.#..\n...#\n");
+
+        a.remove(&Pos::new(3, 2));
+        assert!(a.is_disjoint(&b));
+    }
+
+    #[test]
+    fn masks() {
+        let rows = ["#.##.", ".#..#"].map(|row| row_mask(row, '#'));
+        assert_eq!(rows, [0b01101, 0b10010]);
+        assert_eq!(pack_rows(&rows, 5), Some(0b10010_01101));
+        assert_eq!(pack_rows(&[0; 13], 5), None);
+        assert_eq!(column_counts(&rows, 5), [1, 1, 1, 1, 1]);
+        assert_eq!(column_counts(&[0b11, 0b01], 3), [2, 1, 0]);
+    }
+}
@@ -0,0 +1,135 @@
+use crate::{
+    params::{Params, Schema},
+    rng::Rng,
+    Input,
+};
+use std::{collections::HashMap, fmt::Debug, str::FromStr};
+
+/// Minimal command line arguments: `[subcommand] [--key value | --key=value | --flag]...`
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Args {
+    subcommand: Option<String>,
+    options: HashMap<String, Option<String>>,
+}
+impl Args {
+    pub fn from_env() -> Self {
+        Self::parse(std::env::args().skip(1))
+    }
+
+    pub fn parse<S: Into<String>>(args: impl IntoIterator<Item = S>) -> Self {
+        let mut this = Self::default();
+        let mut args = args.into_iter().map(Into::into).peekable();
+
+        if let Some(first) = args.next_if(|x| !x.starts_with("--")) {
+            this.subcommand = Some(first);
+        }
+
+        while let Some(arg) = args.next() {
+            let Some(key) = arg.strip_prefix("--") else {
+                panic!("Unexpected argument: {arg}");
+            };
+
+            match key.split_once('=') {
+                Some((key, value)) => {
+                    this.options.insert(key.into(), Some(value.into()));
+                }
+                None => {
+                    let value = args.next_if(|x| !x.starts_with("--"));
+                    this.options.insert(key.into(), value);
+                }
+            }
+        }
+
+        this
+    }
+
+    pub fn subcommand(&self) -> Option<&str> {
+        self.subcommand.as_deref()
+    }
+
+    /// Names of every option passed
+    pub fn keys(&self) -> impl Iterator<Item = &str> {
+        self.options.keys().map(String::as_str)
+    }
+
+    /// Whether `--name` was passed, with or without a value
+    pub fn flag(&self, name: &str) -> bool {
+        self.options.contains_key(name)
+    }
+
+    pub fn get<T>(&self, name: &str) -> Option<T>
+    where
+        T: FromStr,
+        T::Err: Debug,
+    {
+        self.options.get(name)?.as_ref().map(|x| {
+            x.parse()
+                .unwrap_or_else(|e| panic!("Invalid --{name}: {e:?}"))
+        })
+    }
+
+    pub fn get_or<T>(&self, name: &str, default: T) -> T
+    where
+        T: FromStr,
+        T::Err: Debug,
+    {
+        self.get(name).unwrap_or(default)
+    }
+
+    /// Puzzle parameters from `schema`, panicking with the accepted
+    /// parameters if any value is invalid
+    pub fn params(&self, schema: &Schema) -> Params {
+        schema
+            .load(self)
+            .unwrap_or_else(|e| panic!("{e}\nParameters:\n{}", schema.help()))
+    }
+
+    /// `--input <path>`, defaulting to stdin
+    pub fn input(&self) -> Input {
+        self.get::<String>("input")
+            .map(Input::file)
+            .unwrap_or_default()
+    }
+
+    /// Handles `generate [--seed N] ...` by printing a generated puzzle input.
+    /// Returns whether the subcommand was run
+    pub fn run_generate(&self, generate: impl FnOnce(&mut Rng, &Self) -> String) -> bool {
+        if self.subcommand() != Some("generate") {
+            return false;
+        }
+
+        let mut rng = Rng::new(self.get_or("seed", 0));
+        print!("{}", generate(&mut rng, self));
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_args() {
+        let args = Args::parse([
+            "generate",
+            "--seed",
+            "42",
+            "--size=10",
+            "--verbose",
+            "--offset",
+            "-3",
+        ]);
+
+        assert_eq!(args.subcommand(), Some("generate"));
+        assert_eq!(args.get::<u64>("seed"), Some(42));
+        assert_eq!(args.get::<u64>("size"), Some(10));
+        assert_eq!(args.get::<i64>("offset"), Some(-3));
+        assert!(args.flag("verbose"));
+        assert!(!args.flag("missing"));
+        assert_eq!(args.get_or("missing", 5), 5);
+
+        let args = Args::parse(["--input", "file.txt"]);
+        assert_eq!(args.subcommand(), None);
+        assert_eq!(args.input(), Input::file("file.txt"));
+    }
+}
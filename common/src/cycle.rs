@@ -0,0 +1,196 @@
+use std::{collections::HashMap, hash::Hash};
+
+/// Shape of an eventually periodic iteration `x0, f(x0), f(f(x0)), ...`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Cycle {
+    /// Iterations before the cycle is entered
+    pub prefix: usize,
+    pub length: usize,
+}
+impl Cycle {
+    /// Earliest iteration with the same state as iteration `n`
+    pub fn reduce(&self, n: u64) -> usize {
+        let prefix = self.prefix as u64;
+        if n < prefix {
+            n as usize
+        } else {
+            (prefix + (n - prefix) % self.length as u64) as usize
+        }
+    }
+}
+
+/// Floyd's tortoise and hare. Constant memory, about three calls of `f` per step
+pub fn floyd<S: Clone + PartialEq>(start: S, f: impl Fn(&S) -> S) -> Cycle {
+    let mut tortoise = f(&start);
+    let mut hare = f(&tortoise);
+    while tortoise != hare {
+        tortoise = f(&tortoise);
+        hare = f(&f(&hare));
+    }
+
+    let mut prefix = 0;
+    tortoise = start;
+    while tortoise != hare {
+        tortoise = f(&tortoise);
+        hare = f(&hare);
+        prefix += 1;
+    }
+
+    let mut length = 1;
+    hare = f(&tortoise);
+    while tortoise != hare {
+        hare = f(&hare);
+        length += 1;
+    }
+
+    Cycle { prefix, length }
+}
+
+/// Brent's algorithm. Constant memory and fewer calls of `f` than Floyd
+pub fn brent<S: Clone + PartialEq>(start: S, f: impl Fn(&S) -> S) -> Cycle {
+    brent_until(start, |s| Some(f(s))).unwrap()
+}
+
+/// Brent's algorithm for iterations that can stop, where `f` returns `None`.
+/// Returns `None` if the iteration stops before it repeats
+pub fn brent_until<S: Clone + PartialEq>(start: S, f: impl Fn(&S) -> Option<S>) -> Option<Cycle> {
+    let mut power = 1;
+    let mut length = 1;
+    let mut tortoise = start.clone();
+    let mut hare = f(&start)?;
+
+    while tortoise != hare {
+        if power == length {
+            tortoise = hare.clone();
+            power *= 2;
+            length = 0;
+        }
+        hare = f(&hare)?;
+        length += 1;
+    }
+
+    // Every state from here on has been seen, so `f` keeps returning `Some`
+    let step = |s: &S| f(s).unwrap();
+
+    tortoise = start.clone();
+    hare = start;
+    for _ in 0..length {
+        hare = step(&hare);
+    }
+
+    let mut prefix = 0;
+    while tortoise != hare {
+        tortoise = step(&tortoise);
+        hare = step(&hare);
+        prefix += 1;
+    }
+
+    Some(Cycle { prefix, length })
+}
+
+/// Remembers every state, calling `f` only once per distinct state
+pub fn hashing<S: Clone + Eq + Hash>(start: S, f: impl Fn(&S) -> S) -> Cycle {
+    let mut seen = HashMap::new();
+    let mut state = start;
+
+    loop {
+        if let Some(prefix) = seen.get(&state) {
+            return Cycle {
+                prefix: *prefix,
+                length: seen.len() - prefix,
+            };
+        }
+
+        let next = f(&state);
+        seen.insert(state, seen.len());
+        state = next;
+    }
+}
+
+/// State after `n` applications of `f`, skipping whole cycles once one is found
+pub fn nth<S: Clone + Eq + Hash>(start: S, f: impl Fn(&S) -> S, n: u64) -> S {
+    let mut seen = HashMap::new();
+    let mut states = vec![];
+    let mut state = start;
+
+    loop {
+        if states.len() as u64 == n {
+            return state;
+        }
+
+        if let Some(prefix) = seen.get(&state) {
+            let cycle = Cycle {
+                prefix: *prefix,
+                length: states.len() - prefix,
+            };
+            return states.swap_remove(cycle.reduce(n));
+        }
+
+        let next = f(&state);
+        seen.insert(state.clone(), states.len());
+        states.push(state);
+        state = next;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 3, 10, 101, 2, 5, 26, 167, 95, 101, ...
+    fn step(x: &u64) -> u64 {
+        (x * x + 1) % 255
+    }
+
+    #[test]
+    fn detectors_agree() {
+        let expected = Cycle {
+            prefix: 2,
+            length: 6,
+        };
+
+        assert_eq!(floyd(3, step), expected);
+        assert_eq!(brent(3, step), expected);
+        assert_eq!(hashing(3, step), expected);
+
+        for start in 0..255 {
+            let cycle = hashing(start, step);
+            assert_eq!(floyd(start, step), cycle);
+            assert_eq!(brent(start, step), cycle);
+        }
+    }
+
+    #[test]
+    fn stopping() {
+        let count_down = |x: &i32| (*x > 0).then(|| x - 1);
+        assert_eq!(brent_until(10, count_down), None);
+
+        let wraps = |x: &i32| Some((x + 1) % 4);
+        assert_eq!(
+            brent_until(0, wraps),
+            Some(Cycle {
+                prefix: 0,
+                length: 4,
+            })
+        );
+    }
+
+    #[test]
+    fn jump() {
+        let cycle = brent(3, step);
+        assert_eq!(cycle.reduce(1), 1);
+        assert_eq!(cycle.reduce(8), 2);
+        assert_eq!(
+            cycle.reduce(1_000_000_000_000),
+            2 + (1_000_000_000_000 - 2) % 6
+        );
+
+        let mut x = 3;
+        for _ in 0..1000 {
+            x = step(&x);
+        }
+        assert_eq!(nth(3, step, 1000), x);
+        assert_eq!(nth(3, step, 0), 3);
+        assert_eq!(nth(3, step, 1_000_000_000_000), nth(3, step, 4));
+    }
+}
@@ -0,0 +1,236 @@
+use crate::Rng;
+use std::{
+    fmt::{Debug, Display},
+    panic::{catch_unwind, AssertUnwindSafe},
+};
+
+/// Result of one implementation, with panics caught as their message
+pub type Outcome<O> = Result<O, String>;
+
+type Implementation<'a, I, O> = Box<dyn Fn(&I) -> O + 'a>;
+
+/// Runs several implementations of the same function and checks they agree
+pub struct Differential<'a, I, O> {
+    implementations: Vec<(&'static str, Implementation<'a, I, O>)>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Disagreement<I, O> {
+    pub input: I,
+    pub outcomes: Vec<(&'static str, Outcome<O>)>,
+}
+impl<I, O> Display for Disagreement<I, O>
+where
+    I: Debug,
+    O: Debug,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "Implementations disagree on input: {:?}", self.input)?;
+        for (name, outcome) in &self.outcomes {
+            match outcome {
+                Ok(x) => writeln!(f, "  {name}: {x:?}")?,
+                Err(e) => writeln!(f, "  {name}: panicked with {e:?}")?,
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<'a, I, O> Differential<'a, I, O>
+where
+    I: Clone,
+    O: PartialEq,
+{
+    pub fn new() -> Self {
+        Self {
+            implementations: vec![],
+        }
+    }
+
+    pub fn register(mut self, name: &'static str, f: impl Fn(&I) -> O + 'a) -> Self {
+        self.implementations.push((name, Box::new(f)));
+        self
+    }
+
+    fn outcomes(&self, input: &I) -> Vec<(&'static str, Outcome<O>)> {
+        self.implementations
+            .iter()
+            .map(|(name, f)| {
+                let outcome = catch_unwind(AssertUnwindSafe(|| f(input))).map_err(|e| {
+                    e.downcast_ref::<&str>()
+                        .map(|x| x.to_string())
+                        .or_else(|| e.downcast_ref::<String>().cloned())
+                        .unwrap_or_default()
+                });
+                (*name, outcome)
+            })
+            .collect()
+    }
+
+    fn disagrees(outcomes: &[(&'static str, Outcome<O>)]) -> bool {
+        outcomes.windows(2).any(|w| w[0].1 != w[1].1)
+    }
+
+    /// Returns the common output, or every outcome if any differ
+    pub fn check(&self, input: &I) -> Result<Outcome<O>, Disagreement<I, O>> {
+        let mut outcomes = self.outcomes(input);
+
+        if Self::disagrees(&outcomes) {
+            Err(Disagreement {
+                input: input.clone(),
+                outcomes,
+            })
+        } else {
+            Ok(outcomes
+                .pop()
+                .map(|x| x.1)
+                .unwrap_or(Err("no implementations".into())))
+        }
+    }
+
+    /// Greedily applies `shrink` while the implementations still disagree
+    pub fn minimize(&self, mut input: I, shrink: impl Fn(&I) -> Vec<I>) -> Disagreement<I, O> {
+        'outer: loop {
+            for candidate in shrink(&input) {
+                if Self::disagrees(&self.outcomes(&candidate)) {
+                    input = candidate;
+                    continue 'outer;
+                }
+            }
+
+            break;
+        }
+
+        Disagreement {
+            outcomes: self.outcomes(&input),
+            input,
+        }
+    }
+
+    /// Checks every input, returning the minimised first disagreement
+    pub fn check_all(
+        &self,
+        inputs: impl IntoIterator<Item = I>,
+        shrink: impl Fn(&I) -> Vec<I>,
+    ) -> Result<(), Disagreement<I, O>> {
+        for input in inputs {
+            if let Err(Disagreement { input, .. }) = self.check(&input) {
+                return Err(self.minimize(input, shrink));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Checks inputs generated from each seed in turn
+    pub fn check_generated(
+        &self,
+        seeds: impl IntoIterator<Item = u64>,
+        generate: impl Fn(&mut Rng) -> I,
+        shrink: impl Fn(&I) -> Vec<I>,
+    ) -> Result<(), Disagreement<I, O>> {
+        let inputs = seeds.into_iter().map(|seed| generate(&mut Rng::new(seed)));
+        self.check_all(inputs, shrink)
+    }
+}
+impl<I, O> Default for Differential<'_, I, O>
+where
+    I: Clone,
+    O: PartialEq,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Smaller versions of a list: both halves, then each element removed
+pub fn shrink_vec<T: Clone>(items: &[T]) -> Vec<Vec<T>> {
+    let mut smaller = vec![];
+
+    if items.len() > 1 {
+        let (a, b) = items.split_at(items.len() / 2);
+        smaller.push(a.to_vec());
+        smaller.push(b.to_vec());
+    }
+
+    for i in 0..items.len() {
+        let mut x = items.to_vec();
+        x.remove(i);
+        smaller.push(x);
+    }
+
+    smaller
+}
+
+/// Smaller versions of a line based input
+pub fn shrink_lines(input: &str) -> Vec<String> {
+    let lines = input.lines().collect::<Vec<_>>();
+    shrink_vec(&lines)
+        .into_iter()
+        .map(|x| x.join("\n"))
+        .collect()
+}
+
+/// Smaller integers, closest to zero first
+pub fn shrink_int(x: u64) -> Vec<u64> {
+    let mut smaller = vec![];
+    let mut delta = x;
+
+    while delta > 0 {
+        smaller.push(x - delta);
+        delta /= 2;
+    }
+
+    smaller
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn agree() {
+        let d = Differential::new()
+            .register("loop", |n: &u64| (1..=*n).sum::<u64>())
+            .register("formula", |n: &u64| n * (n + 1) / 2);
+
+        assert_eq!(d.check(&10), Ok(Ok(55)));
+        assert!(d
+            .check_generated(0..100, |rng| rng.below(1000), |x| shrink_int(*x))
+            .is_ok());
+    }
+
+    #[test]
+    fn minimal_disagreement() {
+        let d = Differential::new()
+            .register("correct", |x: &Vec<i32>| x.iter().sum::<i32>())
+            .register("ignores negatives", |x: &Vec<i32>| {
+                x.iter().filter(|x| **x > 0).sum::<i32>()
+            });
+
+        let err = d
+            .check_all([vec![1, 2, 3], vec![4, -5, 6, 7, 8]], |x| shrink_vec(x))
+            .unwrap_err();
+
+        assert_eq!(err.input, [-5]);
+        assert_eq!(
+            err.outcomes,
+            [("correct", Ok(-5)), ("ignores negatives", Ok(0))]
+        );
+    }
+
+    #[test]
+    fn catches_panics() {
+        let d = Differential::new()
+            .register("checked", |x: &u64| x.checked_sub(10))
+            .register("panics", |x: &u64| {
+                assert!(*x >= 10, "too small");
+                Some(x - 10)
+            });
+
+        let err = d.check_all([100, 50, 5], |x| shrink_int(*x)).unwrap_err();
+
+        assert_eq!(err.input, 0);
+        assert_eq!(err.outcomes[1], ("panics", Err("too small".into())));
+    }
+}
@@ -0,0 +1,328 @@
+use crate::Pos;
+
+/// Disjoint sets with path compression and union by rank
+#[derive(Debug, Default, Clone)]
+pub struct Dsu {
+    parent: Vec<usize>,
+    rank: Vec<u8>,
+    size: Vec<usize>,
+    sets: usize,
+}
+impl Dsu {
+    pub fn new(len: usize) -> Self {
+        Self {
+            parent: (0..len).collect(),
+            rank: vec![0; len],
+            size: vec![1; len],
+            sets: len,
+        }
+    }
+
+    /// Adds a new singleton set, returning its element
+    pub fn push(&mut self) -> usize {
+        let x = self.parent.len();
+        self.parent.push(x);
+        self.rank.push(0);
+        self.size.push(1);
+        self.sets += 1;
+        x
+    }
+
+    pub fn len(&self) -> usize {
+        self.parent.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.parent.is_empty()
+    }
+
+    /// Number of disjoint sets
+    pub fn set_count(&self) -> usize {
+        self.sets
+    }
+
+    pub fn find(&mut self, x: usize) -> usize {
+        let mut root = x;
+        while self.parent[root] != root {
+            root = self.parent[root];
+        }
+
+        let mut x = x;
+        while self.parent[x] != root {
+            x = std::mem::replace(&mut self.parent[x], root);
+        }
+
+        root
+    }
+
+    /// Merges the sets of `a` and `b`, returning whether they were separate
+    pub fn union(&mut self, a: usize, b: usize) -> bool {
+        let (mut a, mut b) = (self.find(a), self.find(b));
+        if a == b {
+            return false;
+        }
+
+        if self.rank[a] < self.rank[b] {
+            std::mem::swap(&mut a, &mut b);
+        }
+        if self.rank[a] == self.rank[b] {
+            self.rank[a] += 1;
+        }
+
+        self.parent[b] = a;
+        self.size[a] += self.size[b];
+        self.sets -= 1;
+        true
+    }
+
+    pub fn same(&mut self, a: usize, b: usize) -> bool {
+        self.find(a) == self.find(b)
+    }
+
+    /// Size of the set containing `x`
+    pub fn size_of(&mut self, x: usize) -> usize {
+        let root = self.find(x);
+        self.size[root]
+    }
+
+    /// Every set, in order of their smallest element
+    pub fn groups(&mut self) -> Vec<Vec<usize>> {
+        let mut index = vec![usize::MAX; self.len()];
+        let mut groups = vec![];
+
+        for x in 0..self.len() {
+            let root = self.find(x);
+            if index[root] == usize::MAX {
+                index[root] = groups.len();
+                groups.push(vec![]);
+            }
+            groups[index[root]].push(x);
+        }
+
+        groups
+    }
+}
+
+/// Union by rank without path compression, so unions can be undone
+#[derive(Debug, Default, Clone)]
+pub struct RollbackDsu {
+    parent: Vec<usize>,
+    rank: Vec<u8>,
+    size: Vec<usize>,
+    sets: usize,
+    // (absorbed root, whether the other root's rank grew)
+    history: Vec<(usize, bool)>,
+}
+impl RollbackDsu {
+    pub fn new(len: usize) -> Self {
+        Self {
+            parent: (0..len).collect(),
+            rank: vec![0; len],
+            size: vec![1; len],
+            sets: len,
+            history: vec![],
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.parent.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.parent.is_empty()
+    }
+
+    pub fn set_count(&self) -> usize {
+        self.sets
+    }
+
+    pub fn find(&self, mut x: usize) -> usize {
+        while self.parent[x] != x {
+            x = self.parent[x];
+        }
+        x
+    }
+
+    /// Merges the sets of `a` and `b`, returning whether they were separate.
+    /// Only successful unions are recorded for [`Self::undo`]
+    pub fn union(&mut self, a: usize, b: usize) -> bool {
+        let (mut a, mut b) = (self.find(a), self.find(b));
+        if a == b {
+            return false;
+        }
+
+        if self.rank[a] < self.rank[b] {
+            std::mem::swap(&mut a, &mut b);
+        }
+        let grew = self.rank[a] == self.rank[b];
+        if grew {
+            self.rank[a] += 1;
+        }
+
+        self.parent[b] = a;
+        self.size[a] += self.size[b];
+        self.sets -= 1;
+        self.history.push((b, grew));
+        true
+    }
+
+    pub fn same(&self, a: usize, b: usize) -> bool {
+        self.find(a) == self.find(b)
+    }
+
+    pub fn size_of(&self, x: usize) -> usize {
+        self.size[self.find(x)]
+    }
+
+    /// Marker for [`Self::rollback`]
+    pub fn snapshot(&self) -> usize {
+        self.history.len()
+    }
+
+    /// Reverts the most recent successful union
+    pub fn undo(&mut self) -> bool {
+        let Some((b, grew)) = self.history.pop() else {
+            return false;
+        };
+
+        let a = self.parent[b];
+        self.parent[b] = b;
+        self.size[a] -= self.size[b];
+        if grew {
+            self.rank[a] -= 1;
+        }
+        self.sets += 1;
+        true
+    }
+
+    /// Reverts every union made since `snapshot`
+    pub fn rollback(&mut self, snapshot: usize) {
+        while self.history.len() > snapshot {
+            self.undo();
+        }
+    }
+}
+
+/// [`Dsu`] over the cells of a `width` × `height` grid
+#[derive(Debug, Clone)]
+pub struct GridDsu {
+    pub width: isize,
+    pub height: isize,
+    sets: Dsu,
+}
+impl GridDsu {
+    pub fn new(width: isize, height: isize) -> Self {
+        Self {
+            width,
+            height,
+            sets: Dsu::new((width * height) as usize),
+        }
+    }
+
+    pub fn index(&self, Pos { x, y }: Pos) -> Option<usize> {
+        let inside = (0..self.width).contains(&x) && (0..self.height).contains(&y);
+        inside.then(|| (y * self.width + x) as usize)
+    }
+
+    pub fn pos(&self, index: usize) -> Pos {
+        let index = index as isize;
+        Pos::new(index % self.width, index / self.width)
+    }
+
+    fn index_of(&self, p: Pos) -> usize {
+        self.index(p)
+            .unwrap_or_else(|| panic!("{p} is outside the grid"))
+    }
+
+    pub fn find(&mut self, p: Pos) -> Pos {
+        let root = self.sets.find(self.index_of(p));
+        self.pos(root)
+    }
+
+    pub fn union(&mut self, a: Pos, b: Pos) -> bool {
+        let (a, b) = (self.index_of(a), self.index_of(b));
+        self.sets.union(a, b)
+    }
+
+    pub fn same(&mut self, a: Pos, b: Pos) -> bool {
+        let (a, b) = (self.index_of(a), self.index_of(b));
+        self.sets.same(a, b)
+    }
+
+    pub fn size_of(&mut self, p: Pos) -> usize {
+        let p = self.index_of(p);
+        self.sets.size_of(p)
+    }
+
+    pub fn groups(&mut self) -> Vec<Vec<Pos>> {
+        self.sets
+            .groups()
+            .into_iter()
+            .map(|g| g.into_iter().map(|i| self.pos(i)).collect())
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn union_find() {
+        let mut dsu = Dsu::new(6);
+        assert!(dsu.union(0, 1));
+        assert!(dsu.union(2, 3));
+        assert!(dsu.union(1, 3));
+        assert!(!dsu.union(0, 2));
+
+        assert!(dsu.same(0, 3));
+        assert!(!dsu.same(0, 4));
+        assert_eq!(dsu.size_of(2), 4);
+        assert_eq!(dsu.set_count(), 3);
+
+        let x = dsu.push();
+        dsu.union(x, 5);
+        assert_eq!(dsu.groups(), [vec![0, 1, 2, 3], vec![4], vec![5, 6]]);
+    }
+
+    #[test]
+    fn rollback() {
+        let mut dsu = RollbackDsu::new(5);
+        dsu.union(0, 1);
+        let snapshot = dsu.snapshot();
+
+        dsu.union(1, 2);
+        dsu.union(3, 4);
+        dsu.union(2, 4);
+        assert_eq!(dsu.size_of(0), 5);
+        assert_eq!(dsu.set_count(), 1);
+
+        assert!(dsu.undo());
+        assert!(!dsu.same(0, 3));
+        assert!(dsu.same(3, 4));
+
+        dsu.rollback(snapshot);
+        assert!(dsu.same(0, 1));
+        assert!(!dsu.same(1, 2));
+        assert!(!dsu.same(3, 4));
+        assert_eq!(dsu.set_count(), 4);
+        assert_eq!(dsu.size_of(1), 2);
+
+        dsu.rollback(0);
+        assert!(!dsu.undo());
+    }
+
+    #[test]
+    fn grid() {
+        let mut dsu = GridDsu::new(3, 2);
+        assert_eq!(dsu.index(Pos::new(2, 1)), Some(5));
+        assert_eq!(dsu.index(Pos::new(3, 0)), None);
+        assert_eq!(dsu.pos(4), Pos::new(1, 1));
+
+        dsu.union(Pos::new(0, 0), Pos::new(1, 0));
+        dsu.union(Pos::new(1, 0), Pos::new(1, 1));
+        assert!(dsu.same(Pos::new(0, 0), Pos::new(1, 1)));
+        assert_eq!(dsu.size_of(Pos::new(1, 1)), 3);
+        assert_eq!(dsu.groups().len(), 4);
+    }
+}
@@ -0,0 +1,734 @@
+use crate::bits::BitSet;
+use std::{
+    borrow::Borrow,
+    cmp::Reverse,
+    collections::{BTreeMap, BTreeSet, BinaryHeap, HashMap},
+    fmt::{Display, Write},
+    hash::Hash,
+};
+
+pub type NodeId = usize;
+
+/// Most sets of placed nodes [`Graph::count_toposorts_of`] remembers, which
+/// keeps its memo to tens of megabytes
+pub const MAX_TOPOSORT_STATES: usize = 1 << 20;
+
+/// Graph over interned nodes. Undirected edges are stored in both directions
+#[derive(Debug, Clone)]
+pub struct Graph<N> {
+    directed: bool,
+    nodes: Vec<N>,
+    ids: HashMap<N, NodeId>,
+    outgoing: Vec<BTreeSet<NodeId>>,
+    incoming: Vec<BTreeSet<NodeId>>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DegreeStats {
+    pub min: usize,
+    pub max: usize,
+    pub mean: f64,
+    /// Degree -> number of nodes with it
+    pub histogram: BTreeMap<usize, usize>,
+}
+
+impl<N> Graph<N>
+where
+    N: Clone + Eq + Hash,
+{
+    fn new(directed: bool) -> Self {
+        Self {
+            directed,
+            nodes: vec![],
+            ids: HashMap::new(),
+            outgoing: vec![],
+            incoming: vec![],
+        }
+    }
+
+    pub fn directed() -> Self {
+        Self::new(true)
+    }
+
+    pub fn undirected() -> Self {
+        Self::new(false)
+    }
+
+    pub fn is_directed(&self) -> bool {
+        self.directed
+    }
+
+    /// Id of `node`, adding it if it is new
+    pub fn intern(&mut self, node: N) -> NodeId {
+        if let Some(id) = self.ids.get(&node) {
+            return *id;
+        }
+
+        let id = self.nodes.len();
+        self.nodes.push(node.clone());
+        self.ids.insert(node, id);
+        self.outgoing.push(BTreeSet::new());
+        self.incoming.push(BTreeSet::new());
+        id
+    }
+
+    pub fn id<Q>(&self, node: &Q) -> Option<NodeId>
+    where
+        N: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.ids.get(node).copied()
+    }
+
+    pub fn node(&self, id: NodeId) -> &N {
+        &self.nodes[id]
+    }
+
+    pub fn nodes(&self) -> impl Iterator<Item = (NodeId, &N)> {
+        self.nodes.iter().enumerate()
+    }
+
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    pub fn add_edge(&mut self, from: N, to: N) -> (NodeId, NodeId) {
+        let from = self.intern(from);
+        let to = self.intern(to);
+        self.add_edge_ids(from, to);
+        (from, to)
+    }
+
+    pub fn add_edge_ids(&mut self, from: NodeId, to: NodeId) {
+        self.outgoing[from].insert(to);
+        self.incoming[to].insert(from);
+
+        if !self.directed {
+            self.outgoing[to].insert(from);
+            self.incoming[from].insert(to);
+        }
+    }
+
+    pub fn has_edge(&self, from: NodeId, to: NodeId) -> bool {
+        self.outgoing[from].contains(&to)
+    }
+
+    /// Outgoing neighbours, in id order
+    pub fn neighbors(&self, id: NodeId) -> impl Iterator<Item = NodeId> + '_ {
+        self.outgoing[id].iter().copied()
+    }
+
+    pub fn predecessors(&self, id: NodeId) -> impl Iterator<Item = NodeId> + '_ {
+        self.incoming[id].iter().copied()
+    }
+
+    pub fn edges(&self) -> impl Iterator<Item = (NodeId, NodeId)> + '_ {
+        let directed = self.directed;
+        self.outgoing.iter().enumerate().flat_map(move |(a, out)| {
+            out.iter()
+                .copied()
+                .filter(move |b| directed || a <= *b)
+                .map(move |b| (a, b))
+        })
+    }
+
+    /// Out degree, which is the degree for undirected graphs
+    pub fn degree(&self, id: NodeId) -> usize {
+        self.outgoing[id].len()
+    }
+
+    pub fn in_degree(&self, id: NodeId) -> usize {
+        self.incoming[id].len()
+    }
+
+    pub fn degree_stats(&self) -> DegreeStats {
+        let mut histogram = BTreeMap::new();
+        for id in 0..self.len() {
+            *histogram.entry(self.degree(id)).or_default() += 1;
+        }
+
+        let total = (0..self.len()).map(|id| self.degree(id)).sum::<usize>();
+        DegreeStats {
+            min: histogram.keys().next().copied().unwrap_or(0),
+            max: histogram.keys().next_back().copied().unwrap_or(0),
+            mean: total as f64 / self.len().max(1) as f64,
+            histogram,
+        }
+    }
+
+    /// Topological order of every node, or a cycle if there is none
+    pub fn toposort(&self) -> Result<Vec<NodeId>, Vec<NodeId>> {
+        self.toposort_of(0..self.len())
+    }
+
+    /// Topological order of `nodes` using only the edges between them. Ties
+    /// keep the order the nodes were given in, and repeated nodes count once.
+    /// On failure returns a cycle
+    pub fn toposort_of(
+        &self,
+        nodes: impl IntoIterator<Item = NodeId>,
+    ) -> Result<Vec<NodeId>, Vec<NodeId>> {
+        let nodes = distinct(nodes);
+        let position = nodes
+            .iter()
+            .enumerate()
+            .map(|(i, id)| (*id, i))
+            .collect::<HashMap<_, _>>();
+
+        let mut remaining = nodes
+            .iter()
+            .map(|id| {
+                self.predecessors(*id)
+                    .filter(|p| position.contains_key(p))
+                    .count()
+            })
+            .collect::<Vec<_>>();
+
+        let mut ready = (0..nodes.len())
+            .filter(|i| remaining[*i] == 0)
+            .map(Reverse)
+            .collect::<BinaryHeap<_>>();
+
+        let mut order = vec![];
+        while let Some(Reverse(i)) = ready.pop() {
+            order.push(nodes[i]);
+
+            for next in self.neighbors(nodes[i]) {
+                if let Some(j) = position.get(&next) {
+                    remaining[*j] -= 1;
+                    if remaining[*j] == 0 {
+                        ready.push(Reverse(*j));
+                    }
+                }
+            }
+        }
+
+        if order.len() == nodes.len() {
+            return Ok(order);
+        }
+
+        // Every unordered node has an unordered predecessor, so walking
+        // backwards must eventually repeat
+        let mut path = vec![];
+        let mut seen = HashMap::new();
+        let mut current = (0..nodes.len()).find(|i| remaining[*i] > 0).unwrap();
+        while !seen.contains_key(&current) {
+            seen.insert(current, path.len());
+            path.push(nodes[current]);
+            current = self
+                .predecessors(nodes[current])
+                .filter_map(|p| position.get(&p).copied())
+                .find(|j| remaining[*j] > 0)
+                .unwrap();
+        }
+
+        let mut cycle = path.split_off(seen[&current]);
+        cycle.reverse();
+
+        // Start from whichever cycle node was given first
+        let first = (0..cycle.len())
+            .min_by_key(|i| position[&cycle[*i]])
+            .unwrap();
+        cycle.rotate_left(first);
+        Err(cycle)
+    }
+
+    /// Number of topological orders of `nodes` using only the edges between
+    /// them, or a cycle if there are none. `None` if there are more than 64
+    /// nodes, the count needs more than [`MAX_TOPOSORT_STATES`] sets of placed
+    /// nodes, or it doesn't fit in a `u128`
+    pub fn count_toposorts_of(
+        &self,
+        nodes: impl IntoIterator<Item = NodeId>,
+    ) -> Result<Option<u128>, Vec<NodeId>> {
+        let nodes = distinct(nodes);
+        self.toposort_of(nodes.iter().copied())?;
+        if nodes.len() > 64 {
+            return Ok(None);
+        }
+
+        // Orders only differ in which ready node goes next, so the count
+        // depends on nothing but the set of nodes already placed
+        fn count(placed: u64, before: &[u64], memo: &mut HashMap<u64, u128>) -> Option<u128> {
+            if placed.count_ones() as usize == before.len() {
+                return Some(1);
+            }
+            if let Some(n) = memo.get(&placed) {
+                return Some(*n);
+            }
+            if memo.len() >= MAX_TOPOSORT_STATES {
+                return None;
+            }
+
+            let n = (0..before.len())
+                .filter(|i| placed & 1 << i == 0 && before[*i] & !placed == 0)
+                .try_fold(0u128, |n, i| {
+                    n.checked_add(count(placed | 1 << i, before, memo)?)
+                })?;
+            memo.insert(placed, n);
+            Some(n)
+        }
+
+        Ok(count(
+            0,
+            &self.predecessor_masks(&nodes),
+            &mut HashMap::new(),
+        ))
+    }
+
+    /// Up to `limit` topological orders of `nodes` using only the edges
+    /// between them, in the order ties are broken by [`Self::toposort_of`]
+    pub fn toposorts_of(
+        &self,
+        nodes: impl IntoIterator<Item = NodeId>,
+        limit: usize,
+    ) -> Result<Vec<Vec<NodeId>>, Vec<NodeId>> {
+        let nodes = distinct(nodes);
+        self.toposort_of(nodes.iter().copied())?;
+
+        fn extend(
+            order: &mut Vec<usize>,
+            placed: &mut BitSet,
+            before: &[BitSet],
+            limit: usize,
+            orders: &mut Vec<Vec<usize>>,
+        ) {
+            if order.len() == before.len() {
+                orders.push(order.clone());
+                return;
+            }
+
+            for i in 0..before.len() {
+                if orders.len() == limit {
+                    return;
+                }
+                if !placed.contains(i) && before[i].is_subset(placed) {
+                    order.push(i);
+                    placed.insert(i);
+                    extend(order, placed, before, limit, orders);
+                    placed.remove(i);
+                    order.pop();
+                }
+            }
+        }
+
+        let mut orders = vec![];
+        let before = self.predecessor_sets(&nodes);
+        extend(
+            &mut vec![],
+            &mut BitSet::with_capacity(nodes.len()),
+            &before,
+            limit,
+            &mut orders,
+        );
+
+        Ok(orders
+            .into_iter()
+            .map(|order| order.into_iter().map(|i| nodes[i]).collect())
+            .collect())
+    }
+
+    /// For each of `nodes`, the positions of its predecessors among them
+    fn predecessor_sets(&self, nodes: &[NodeId]) -> Vec<BitSet> {
+        let position = nodes
+            .iter()
+            .enumerate()
+            .map(|(i, id)| (*id, i))
+            .collect::<HashMap<_, _>>();
+
+        nodes
+            .iter()
+            .map(|id| {
+                self.predecessors(*id)
+                    .filter_map(|p| position.get(&p).copied())
+                    .collect()
+            })
+            .collect()
+    }
+
+    /// For each of `nodes`, the bitmask of its predecessors among them. At
+    /// most 64 nodes
+    fn predecessor_masks(&self, nodes: &[NodeId]) -> Vec<u64> {
+        assert!(nodes.len() <= 64, "too many nodes for a bitmask");
+
+        self.predecessor_sets(nodes)
+            .iter()
+            .map(|before| before.iter().fold(0, |mask, i| mask | 1 << i))
+            .collect()
+    }
+
+    /// Strongly connected components (Tarjan), each in discovery order
+    pub fn strongly_connected_components(&self) -> Vec<Vec<NodeId>> {
+        const UNVISITED: usize = usize::MAX;
+
+        let n = self.len();
+        let mut index = vec![UNVISITED; n];
+        let mut low = vec![0; n];
+        let mut on_stack = vec![false; n];
+        let mut stack = vec![];
+        let mut components = vec![];
+        let mut counter = 0;
+
+        for root in 0..n {
+            if index[root] != UNVISITED {
+                continue;
+            }
+
+            // (node, neighbours still to visit)
+            let mut work = vec![(root, self.neighbors(root).collect::<Vec<_>>())];
+            index[root] = counter;
+            low[root] = counter;
+            counter += 1;
+            stack.push(root);
+            on_stack[root] = true;
+
+            while let Some((node, pending)) = work.last_mut() {
+                let node = *node;
+
+                if let Some(next) = pending.pop() {
+                    if index[next] == UNVISITED {
+                        index[next] = counter;
+                        low[next] = counter;
+                        counter += 1;
+                        stack.push(next);
+                        on_stack[next] = true;
+                        work.push((next, self.neighbors(next).collect()));
+                    } else if on_stack[next] {
+                        low[node] = low[node].min(index[next]);
+                    }
+                    continue;
+                }
+
+                work.pop();
+                if let Some((parent, _)) = work.last() {
+                    low[*parent] = low[*parent].min(low[node]);
+                }
+
+                if low[node] == index[node] {
+                    let mut component = vec![];
+                    loop {
+                        let x = stack.pop().unwrap();
+                        on_stack[x] = false;
+                        component.push(x);
+                        if x == node {
+                            break;
+                        }
+                    }
+                    component.reverse();
+                    components.push(component);
+                }
+            }
+        }
+
+        components
+    }
+
+    /// Every triangle `[a, b, c]` with `a < b < c`
+    pub fn triangles(&self) -> Vec<[NodeId; 3]> {
+        let mut triangles = vec![];
+        for a in 0..self.len() {
+            let higher = self.outgoing[a].range(a + 1..).copied().collect::<Vec<_>>();
+            for (i, b) in higher.iter().enumerate() {
+                for c in &higher[i + 1..] {
+                    if self.has_edge(*b, *c) {
+                        triangles.push([a, *b, *c]);
+                    }
+                }
+            }
+        }
+        triangles
+    }
+
+    /// Every maximal clique (Bron–Kerbosch with pivoting), each sorted by id
+    pub fn maximal_cliques(&self) -> Vec<Vec<NodeId>> {
+        fn expand<N>(
+            g: &Graph<N>,
+            current: &mut Vec<NodeId>,
+            mut candidates: BTreeSet<NodeId>,
+            mut excluded: BTreeSet<NodeId>,
+            out: &mut Vec<Vec<NodeId>>,
+        ) {
+            if candidates.is_empty() && excluded.is_empty() {
+                let mut clique = current.clone();
+                clique.sort();
+                out.push(clique);
+                return;
+            }
+
+            let pivot = candidates
+                .union(&excluded)
+                .max_by_key(|p| g.outgoing[**p].intersection(&candidates).count())
+                .copied()
+                .unwrap();
+
+            let choices = candidates
+                .difference(&g.outgoing[pivot])
+                .copied()
+                .collect::<Vec<_>>();
+
+            for v in choices {
+                let neighbors = &g.outgoing[v];
+                current.push(v);
+                expand(
+                    g,
+                    current,
+                    candidates.intersection(neighbors).copied().collect(),
+                    excluded.intersection(neighbors).copied().collect(),
+                    out,
+                );
+                current.pop();
+
+                candidates.remove(&v);
+                excluded.insert(v);
+            }
+        }
+
+        let mut out = vec![];
+        let all = (0..self.len()).collect();
+        expand(self, &mut vec![], all, BTreeSet::new(), &mut out);
+        out.sort();
+        out
+    }
+
+    /// A largest clique, the first in id order on ties
+    pub fn maximum_clique(&self) -> Vec<NodeId> {
+        self.maximal_cliques()
+            .into_iter()
+            .rev()
+            .max_by_key(|c| c.len())
+            .unwrap_or_default()
+    }
+
+    /// Graphviz source, with node attributes from `attributes` (e.g. `label="x"`)
+    pub fn to_dot_with(&self, attributes: impl Fn(NodeId, &N) -> String) -> String {
+        let (kind, arrow) = match self.directed {
+            true => ("digraph", "->"),
+            false => ("graph", "--"),
+        };
+
+        let mut out = format!("{kind} {{\n");
+        for (id, node) in self.nodes() {
+            writeln!(out, "  n{id} [{}];", attributes(id, node)).unwrap();
+        }
+        for (a, b) in self.edges() {
+            writeln!(out, "  n{a} {arrow} n{b};").unwrap();
+        }
+        out.push_str("}\n");
+        out
+    }
+
+    pub fn to_dot(&self) -> String
+    where
+        N: Display,
+    {
+        self.to_dot_with(|_, node| format!("label=\"{node}\""))
+    }
+}
+
+/// `nodes` without repeats, keeping the first of each
+fn distinct(nodes: impl IntoIterator<Item = NodeId>) -> Vec<NodeId> {
+    let mut seen = BTreeSet::new();
+    nodes.into_iter().filter(|id| seen.insert(*id)).collect()
+}
+
+impl<N> FromIterator<(N, N)> for Graph<N>
+where
+    N: Clone + Eq + Hash,
+{
+    /// Collects into a directed graph
+    fn from_iter<T: IntoIterator<Item = (N, N)>>(iter: T) -> Self {
+        let mut graph = Self::directed();
+        for (a, b) in iter {
+            graph.add_edge(a, b);
+        }
+        graph
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Tap;
+
+    fn undirected(edges: &[(&'static str, &'static str)]) -> Graph<&'static str> {
+        let mut g = Graph::undirected();
+        for (a, b) in edges {
+            g.add_edge(*a, *b);
+        }
+        g
+    }
+
+    fn names<'a>(g: &Graph<&'a str>, ids: &[NodeId]) -> Vec<&'a str> {
+        ids.iter().map(|id| *g.node(*id)).collect()
+    }
+
+    #[test]
+    fn interning() {
+        let mut g = Graph::directed();
+        let (a, b) = g.add_edge("a".to_string(), "b".to_string());
+        g.add_edge("a".to_string(), "b".to_string());
+
+        assert_eq!(g.len(), 2);
+        assert_eq!(g.id("a"), Some(a));
+        assert_eq!(g.id("c"), None);
+        assert!(g.has_edge(a, b));
+        assert!(!g.has_edge(b, a));
+        assert_eq!(g.edges().collect::<Vec<_>>(), [(a, b)]);
+    }
+
+    #[test]
+    fn topological() {
+        let g = [(5, 3), (3, 1), (5, 1), (2, 1)]
+            .into_iter()
+            .collect::<Graph<_>>();
+        let order = g.toposort().unwrap();
+        assert_eq!(
+            order.iter().map(|id| *g.node(*id)).collect::<Vec<_>>(),
+            [5, 3, 2, 1]
+        );
+
+        // Only edges within the subset count, ties keep input order
+        let subset = [g.id(&1).unwrap(), g.id(&2).unwrap(), g.id(&3).unwrap()];
+        let order = g.toposort_of(subset).unwrap();
+        assert_eq!(
+            order.iter().map(|id| *g.node(*id)).collect::<Vec<_>>(),
+            [2, 3, 1]
+        );
+
+        let g = [("a", "b"), ("b", "c"), ("c", "d"), ("d", "b"), ("x", "a")]
+            .into_iter()
+            .collect::<Graph<_>>();
+        let cycle = g.toposort().unwrap_err();
+        assert_eq!(names(&g, &cycle), ["b", "c", "d"]);
+
+        // Repeats count once
+        let g = [("a", "b")].into_iter().collect::<Graph<_>>();
+        let (a, b) = (g.id("a").unwrap(), g.id("b").unwrap());
+        assert_eq!(g.toposort_of([a, b, a]), Ok(vec![a, b]));
+        assert_eq!(g.toposort_of([b, a, b]), Ok(vec![a, b]));
+    }
+
+    #[test]
+    fn all_topological_orders() {
+        let g = [("a", "c"), ("b", "c"), ("c", "d"), ("c", "e")]
+            .into_iter()
+            .collect::<Graph<_>>();
+        let ids = ["a", "b", "c", "d", "e"].map(|x| g.id(x).unwrap());
+
+        assert_eq!(g.count_toposorts_of(ids), Ok(Some(4)));
+        let orders = g.toposorts_of(ids, 10).unwrap();
+        assert_eq!(orders.len(), 4);
+        assert_eq!(orders[0], g.toposort_of(ids).unwrap());
+        assert_eq!(names(&g, &orders[3]), ["b", "a", "c", "e", "d"]);
+        assert_eq!(g.toposorts_of(ids, 2).unwrap().len(), 2);
+
+        // Unrelated nodes can go in any order
+        let (x, y) = (g.id("d").unwrap(), g.id("e").unwrap());
+        assert_eq!(g.count_toposorts_of([ids[0], ids[1], x, y]), Ok(Some(24)));
+        assert_eq!(g.count_toposorts_of([ids[0], ids[1], ids[0]]), Ok(Some(2)));
+        assert_eq!(g.count_toposorts_of([]), Ok(Some(1)));
+        assert_eq!(g.toposorts_of([ids[0], ids[0]], 5), Ok(vec![vec![ids[0]]]));
+
+        let g = [("a", "b"), ("b", "a")].into_iter().collect::<Graph<_>>();
+        assert!(g.count_toposorts_of(0..2).is_err());
+        assert!(g.toposorts_of(0..2, 1).is_err());
+    }
+
+    #[test]
+    fn topological_order_limits() {
+        // Six chains of ten can be partly placed in 11^6 ways, more than are
+        // remembered
+        let mut g = Graph::directed();
+        for chain in 0..6 {
+            for i in 1..10 {
+                g.add_edge((chain, i - 1), (chain, i));
+            }
+        }
+        assert_eq!(g.count_toposorts_of(0..g.len()), Ok(None));
+
+        // Chains interleave in 60! / 12!^5 ways, which just fits, but a
+        // longer chain makes too many
+        let mut g = Graph::directed();
+        for chain in 0..5 {
+            for i in 1..12 {
+                g.add_edge((chain, i - 1), (chain, i));
+            }
+        }
+        assert_eq!(
+            g.count_toposorts_of(0..g.len()),
+            Ok(Some(329_981_831_728_425_465_309_559_251_123_033_960_000))
+        );
+        g.add_edge((4, 11), (4, 12));
+        assert_eq!(g.count_toposorts_of(0..g.len()), Ok(None));
+
+        // Orders can still be listed past 64 nodes
+        let mut g = Graph::directed();
+        let ids = (0..70).map(|i| g.intern(i)).collect::<Vec<_>>();
+        g.add_edge_ids(ids[69], ids[0]);
+        assert_eq!(g.count_toposorts_of(ids.clone()), Ok(None));
+        let orders = g.toposorts_of(ids.clone(), 2).unwrap();
+        assert_eq!(orders.len(), 2);
+        assert_eq!(orders[0], g.toposort_of(ids).unwrap());
+        assert_eq!(orders[0][..2], [1, 2]);
+        assert_eq!(orders[1][..2], [1, 2]);
+    }
+
+    #[test]
+    fn components() {
+        let g = [
+            ("a", "b"),
+            ("b", "a"),
+            ("b", "c"),
+            ("c", "d"),
+            ("d", "c"),
+            ("d", "e"),
+        ]
+        .into_iter()
+        .collect::<Graph<_>>();
+
+        let mut sccs = g
+            .strongly_connected_components()
+            .iter()
+            .map(|c| names(&g, c).tap_mut(|x| x.sort()))
+            .collect::<Vec<_>>();
+        sccs.sort();
+        assert_eq!(sccs, [vec!["a", "b"], vec!["c", "d"], vec!["e"]]);
+    }
+
+    #[test]
+    fn cliques() {
+        let g = undirected(&[
+            ("a", "b"),
+            ("a", "c"),
+            ("b", "c"),
+            ("c", "d"),
+            ("d", "e"),
+            ("e", "f"),
+            ("d", "f"),
+            ("c", "e"),
+            ("c", "f"),
+        ]);
+
+        assert_eq!(g.triangles().len(), 5);
+        assert_eq!(g.maximal_cliques().len(), 2);
+        assert_eq!(names(&g, &g.maximum_clique()), ["c", "d", "e", "f"]);
+
+        let stats = g.degree_stats();
+        assert_eq!((stats.min, stats.max), (2, 5));
+        assert_eq!(stats.mean, 18.0 / 6.0);
+        assert_eq!(stats.histogram[&3], 3);
+    }
+
+    #[test]
+    fn dot() {
+        let g = undirected(&[("a", "b")]);
+        assert_eq!(
+            g.to_dot(),
+            "graph {\n  n0 [label=\"a\"];\n  n1 [label=\"b\"];\n  n0 -- n1;\n}\n"
+        );
+    }
+}
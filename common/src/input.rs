@@ -0,0 +1,166 @@
+use memmap2::Mmap;
+use std::{
+    fs::File,
+    io::{BufRead, BufReader, Read},
+    ops::Deref,
+    path::{Path, PathBuf},
+};
+
+/// Where puzzle input is read from
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub enum Input {
+    #[default]
+    Stdin,
+    File(PathBuf),
+}
+impl Input {
+    pub fn stdin() -> Self {
+        Self::Stdin
+    }
+
+    pub fn file(path: impl AsRef<Path>) -> Self {
+        Self::File(path.as_ref().to_owned())
+    }
+
+    fn reader(&self) -> Box<dyn BufRead> {
+        match self {
+            Self::Stdin => Box::new(std::io::stdin().lock()),
+            Self::File(path) => Box::new(BufReader::new(File::open(path).unwrap())),
+        }
+    }
+
+    /// Reads the whole input into memory
+    pub fn read_string(&self) -> String {
+        let mut buf = String::new();
+        self.reader().read_to_string(&mut buf).unwrap();
+        buf
+    }
+
+    /// Buffered iterator over lines, without trailing newlines
+    pub fn lines(&self) -> impl Iterator<Item = String> {
+        self.reader().lines().map(Result::unwrap)
+    }
+
+    /// Buffered iterator over blank-line separated records
+    pub fn records(&self) -> Records<impl Iterator<Item = String>> {
+        Records {
+            lines: self.lines(),
+        }
+    }
+
+    /// Maps the input into memory if possible, falling back to reading it
+    /// (e.g. when stdin is a pipe)
+    pub fn bytes(&self) -> Bytes {
+        let mapped = match self {
+            Self::Stdin => map_stdin(),
+            Self::File(path) => {
+                let file = File::open(path).unwrap();
+                unsafe { Mmap::map(&file) }.ok()
+            }
+        };
+
+        match mapped {
+            Some(map) => Bytes::Mapped(map),
+            None => {
+                let mut buf = vec![];
+                self.reader().read_to_end(&mut buf).unwrap();
+                Bytes::Owned(buf)
+            }
+        }
+    }
+}
+
+#[cfg(unix)]
+fn map_stdin() -> Option<Mmap> {
+    let stdin = std::io::stdin();
+    unsafe { Mmap::map(&stdin) }.ok()
+}
+
+#[cfg(not(unix))]
+fn map_stdin() -> Option<Mmap> {
+    None
+}
+
+/// Input bytes, either memory mapped or read into a buffer
+#[derive(Debug)]
+pub enum Bytes {
+    Mapped(Mmap),
+    Owned(Vec<u8>),
+}
+impl Deref for Bytes {
+    type Target = [u8];
+    fn deref(&self) -> &Self::Target {
+        match self {
+            Self::Mapped(map) => map,
+            Self::Owned(buf) => buf,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct Records<I> {
+    lines: I,
+}
+impl<I> Iterator for Records<I>
+where
+    I: Iterator<Item = String>,
+{
+    type Item = Vec<String>;
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut record = vec![];
+
+        for line in self.lines.by_ref() {
+            if line.is_empty() {
+                if record.is_empty() {
+                    continue;
+                }
+                break;
+            }
+
+            record.push(line);
+        }
+
+        if record.is_empty() {
+            None
+        } else {
+            Some(record)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    fn temp_file(name: &str, contents: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("common_input_{name}"));
+        File::create(&path)
+            .unwrap()
+            .write_all(contents.as_bytes())
+            .unwrap();
+        path
+    }
+
+    #[test]
+    fn lines_and_records() {
+        let path = temp_file("records", "a\nb\n\nc\n\n\nd\ne\n");
+        let input = Input::file(&path);
+
+        assert_eq!(input.lines().count(), 8);
+        assert_eq!(
+            input.records().collect::<Vec<_>>(),
+            [vec!["a", "b"], vec!["c"], vec!["d", "e"]]
+        );
+        assert_eq!(input.read_string(), "a\nb\n\nc\n\n\nd\ne\n");
+    }
+
+    #[test]
+    fn mapped_bytes() {
+        let path = temp_file("bytes", "12345");
+        let bytes = Input::file(&path).bytes();
+
+        assert!(matches!(bytes, Bytes::Mapped(_)));
+        assert_eq!(&*bytes, b"12345");
+    }
+}
@@ -0,0 +1,176 @@
+use std::{collections::HashMap, fmt::Display, ops::Index, str::FromStr};
+
+/// Handle for a name in an [`Interner`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Symbol(u32);
+impl Symbol {
+    /// Dense index, usable for `Vec` lookups
+    pub fn index(self) -> usize {
+        self.0 as usize
+    }
+}
+
+/// Maps names to compact symbols, in order of first appearance
+#[derive(Debug, Default, Clone)]
+pub struct Interner {
+    names: Vec<Box<str>>,
+    symbols: HashMap<Box<str>, Symbol>,
+}
+impl Interner {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Symbol for `name`, adding it if it is new
+    pub fn intern(&mut self, name: &str) -> Symbol {
+        if let Some(symbol) = self.symbols.get(name) {
+            return *symbol;
+        }
+
+        let symbol = Symbol(
+            self.names
+                .len()
+                .try_into()
+                .expect("more than u32::MAX names"),
+        );
+        self.names.push(name.into());
+        self.symbols.insert(name.into(), symbol);
+        symbol
+    }
+
+    pub fn get(&self, name: &str) -> Option<Symbol> {
+        self.symbols.get(name).copied()
+    }
+
+    pub fn resolve(&self, symbol: Symbol) -> &str {
+        &self.names[symbol.index()]
+    }
+
+    pub fn len(&self) -> usize {
+        self.names.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.names.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (Symbol, &str)> {
+        self.names
+            .iter()
+            .enumerate()
+            .map(|(i, name)| (Symbol(i as u32), &**name))
+    }
+}
+impl Index<Symbol> for Interner {
+    type Output = str;
+
+    fn index(&self, symbol: Symbol) -> &str {
+        self.resolve(symbol)
+    }
+}
+
+/// Name of exactly `N` characters from `[0-9a-z]`, packed in base 36.
+/// Packed names order the same way as the strings they came from
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Packed<const N: usize>(u32);
+
+pub type Name2 = Packed<2>;
+pub type Name3 = Packed<3>;
+
+impl<const N: usize> Packed<N> {
+    /// Number of distinct names, an upper bound for [`Self::index`]
+    pub const LIMIT: usize = 36usize.pow(N as u32);
+
+    pub fn new(name: &str) -> Option<Self> {
+        if name.len() != N {
+            return None;
+        }
+
+        name.bytes()
+            .try_fold(0, |acc, b| {
+                let digit = match b {
+                    b'0'..=b'9' => b - b'0',
+                    b'a'..=b'z' => b - b'a' + 10,
+                    _ => return None,
+                };
+                Some(acc * 36 + digit as u32)
+            })
+            .map(Self)
+    }
+
+    pub fn index(self) -> usize {
+        self.0 as usize
+    }
+
+    pub fn from_index(index: usize) -> Option<Self> {
+        (index < Self::LIMIT).then_some(Self(index as u32))
+    }
+
+    /// Character `i`, counting from the left
+    pub fn char_at(self, i: usize) -> char {
+        assert!(i < N, "index {i} out of range for a name of {N}");
+        let digit = self.0 / 36u32.pow((N - 1 - i) as u32) % 36;
+        char::from_digit(digit, 36).unwrap()
+    }
+
+    pub fn starts_with(self, c: char) -> bool {
+        self.char_at(0) == c
+    }
+}
+impl<const N: usize> Display for Packed<N> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        (0..N).try_for_each(|i| write!(f, "{}", self.char_at(i)))
+    }
+}
+impl<const N: usize> std::fmt::Debug for Packed<N> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{self}")
+    }
+}
+impl<const N: usize> FromStr for Packed<N> {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::new(s).ok_or_else(|| format!("{s:?} is not {N} characters of [0-9a-z]"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn interner() {
+        let mut names = Interner::new();
+        let kh = names.intern("kh");
+        let tc = names.intern("tc");
+
+        assert_eq!(names.intern("kh"), kh);
+        assert_eq!(names.get("tc"), Some(tc));
+        assert_eq!(names.get("qp"), None);
+        assert_eq!(&names[tc], "tc");
+        assert_eq!(tc.index(), 1);
+        assert_eq!(names.iter().collect::<Vec<_>>(), [(kh, "kh"), (tc, "tc")]);
+    }
+
+    #[test]
+    fn packed() {
+        let z05 = Name3::new("z05").unwrap();
+        assert_eq!(z05.to_string(), "z05");
+        assert!(z05.starts_with('z'));
+        assert_eq!(z05.char_at(2), '5');
+        assert_eq!(Name3::from_index(z05.index()), Some(z05));
+
+        assert_eq!(Name2::new("TC"), None);
+        assert_eq!(Name2::new("abc"), None);
+        assert!("x1".parse::<Name3>().is_err());
+        assert_eq!(Name2::LIMIT, 1296);
+        assert_eq!(Name2::from_index(1296), None);
+
+        let mut names = ["z10", "x00", "y01", "abc", "z09"];
+        let mut packed = names.map(|x| Name3::new(x).unwrap());
+        names.sort();
+        packed.sort();
+        assert_eq!(packed.map(|x| x.to_string()), names);
+    }
+}
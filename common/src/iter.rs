@@ -48,14 +48,14 @@ where
 {
 }
 
-pub fn pairs_iter<T>(
-    mut iter: impl Iterator<Item = T>,
-) -> FromFn<impl FnMut() -> Option<(T, Option<T>)>> {
+type Pair<T> = (T, Option<T>);
+
+pub fn pairs_iter<T>(mut iter: impl Iterator<Item = T>) -> FromFn<impl FnMut() -> Option<Pair<T>>> {
     from_fn(move || iter.next().map(|a| (a, iter.next())))
 }
 
 pub trait PairsIter: Iterator + Sized {
-    fn pairs(self) -> FromFn<impl FnMut() -> Option<(Self::Item, Option<Self::Item>)>> {
+    fn pairs(self) -> FromFn<impl FnMut() -> Option<Pair<Self::Item>>> {
         pairs_iter(self)
     }
 }
@@ -1,17 +1,35 @@
+pub mod bits;
+pub mod cli;
+pub mod cycle;
+pub mod differential;
+pub mod dsu;
+pub mod graph;
 pub mod grid;
+pub mod input;
+pub mod intern;
+pub mod linalg;
+pub mod math;
+pub mod params;
+pub mod parse;
 pub mod pipe;
 pub mod pos;
+pub mod profile;
+pub mod queue;
+pub mod ranges;
+pub mod rng;
+pub mod search;
 pub mod vectors;
 pub mod iter;
 
+pub use cli::Args;
+pub use differential::Differential;
 pub use grid::Grid;
+pub use input::Input;
 pub use pipe::{Pipe, Tap};
 pub use pos::Pos;
+pub use rng::Rng;
 
-use std::{
-    io::Read,
-    time::{Duration, Instant},
-};
+use std::time::{Duration, Instant};
 
 pub fn timed<Ret>(func: impl FnOnce() -> Ret) -> (Duration, Ret) {
     let start = Instant::now();
@@ -23,11 +41,9 @@ pub fn timed<Ret>(func: impl FnOnce() -> Ret) -> (Duration, Ret) {
 
 fn rolling_mean(items: impl IntoIterator<Item = Duration>) -> Duration {
     let mut average = Duration::from_secs(0);
-    let mut iterations = 1;
 
-    for x in items {
+    for (x, iterations) in items.into_iter().zip(1..) {
         average += (x.saturating_sub(average)) / iterations;
-        iterations += 1;
     }
 
     average
@@ -47,9 +63,7 @@ pub fn timed_repeated<Ret>(repeats: u32, func: impl Fn() -> Ret) -> (Duration, R
 }
 
 pub fn read_stdin() -> String {
-    let mut buf = String::new();
-    std::io::stdin().read_to_string(&mut buf).unwrap();
-    buf
+    Input::stdin().read_string()
 }
 
 #[cfg(test)]
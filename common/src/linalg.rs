@@ -0,0 +1,451 @@
+use std::{
+    cmp::Ordering,
+    fmt::Display,
+    ops::{Add, Div, Mul, Neg, Sub},
+};
+
+fn gcd(a: i128, b: i128) -> i128 {
+    let (mut a, mut b) = (a.unsigned_abs(), b.unsigned_abs());
+
+    // 64-bit division is much faster than 128-bit, and most values fit
+    if let (Ok(mut a), Ok(mut b)) = (u64::try_from(a), u64::try_from(b)) {
+        while b != 0 {
+            (a, b) = (b, a % b);
+        }
+        return a as i128;
+    }
+
+    while b != 0 {
+        (a, b) = (b, a % b);
+    }
+    a as i128
+}
+
+/// `a / b`, in 64 bits when both fit
+fn div(a: i128, b: i128) -> i128 {
+    match (i64::try_from(a), i64::try_from(b)) {
+        (Ok(a), Ok(b)) if b != -1 => (a / b) as i128,
+        _ => a / b,
+    }
+}
+
+/// Exact fraction, always stored reduced with a positive denominator
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Rational {
+    num: i128,
+    den: i128,
+}
+impl Rational {
+    pub const ZERO: Self = Self { num: 0, den: 1 };
+    pub const ONE: Self = Self { num: 1, den: 1 };
+
+    pub fn new(num: i128, den: i128) -> Self {
+        Self::checked_new(num, den).expect("zero denominator")
+    }
+
+    pub fn checked_new(num: i128, den: i128) -> Option<Self> {
+        if den == 0 {
+            return None;
+        }
+
+        // Whole numbers are common and need no gcd
+        if num % den == 0 {
+            return Some(Self {
+                num: num.checked_div(den)?,
+                den: 1,
+            });
+        }
+
+        let g = gcd(num, den);
+        let sign = den.signum();
+        Some(Self {
+            num: sign.checked_mul(div(num, g))?,
+            den: sign.checked_mul(div(den, g))?,
+        })
+    }
+
+    pub fn num(&self) -> i128 {
+        self.num
+    }
+
+    pub fn den(&self) -> i128 {
+        self.den
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.num == 0
+    }
+
+    pub fn is_integer(&self) -> bool {
+        self.den == 1
+    }
+
+    pub fn to_integer(&self) -> Option<i128> {
+        self.is_integer().then_some(self.num)
+    }
+
+    pub fn checked_add(self, rhs: Self) -> Option<Self> {
+        let g = gcd(self.den, rhs.den);
+        let num =
+            (self.num.checked_mul(rhs.den / g)?).checked_add(rhs.num.checked_mul(self.den / g)?)?;
+        Self::checked_new(num, (self.den / g).checked_mul(rhs.den)?)
+    }
+
+    pub fn checked_sub(self, rhs: Self) -> Option<Self> {
+        self.checked_add(-rhs)
+    }
+
+    pub fn checked_mul(self, rhs: Self) -> Option<Self> {
+        // Cross reduce first to keep intermediates small
+        let g1 = gcd(self.num, rhs.den).max(1);
+        let g2 = gcd(rhs.num, self.den).max(1);
+        Self::checked_new(
+            (self.num / g1).checked_mul(rhs.num / g2)?,
+            (self.den / g2).checked_mul(rhs.den / g1)?,
+        )
+    }
+
+    pub fn checked_div(self, rhs: Self) -> Option<Self> {
+        if rhs.is_zero() {
+            return None;
+        }
+        self.checked_mul(Self {
+            num: rhs.den * rhs.num.signum(),
+            den: rhs.num.abs(),
+        })
+    }
+}
+impl Default for Rational {
+    fn default() -> Self {
+        Self::ZERO
+    }
+}
+impl Display for Rational {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.is_integer() {
+            write!(f, "{}", self.num)
+        } else {
+            write!(f, "{}/{}", self.num, self.den)
+        }
+    }
+}
+impl From<i32> for Rational {
+    fn from(value: i32) -> Self {
+        Self::from(value as i128)
+    }
+}
+impl From<i64> for Rational {
+    fn from(value: i64) -> Self {
+        Self::from(value as i128)
+    }
+}
+impl From<i128> for Rational {
+    fn from(value: i128) -> Self {
+        Self { num: value, den: 1 }
+    }
+}
+impl PartialOrd for Rational {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+impl Ord for Rational {
+    fn cmp(&self, other: &Self) -> Ordering {
+        match (
+            self.num.checked_mul(other.den),
+            other.num.checked_mul(self.den),
+        ) {
+            (Some(a), Some(b)) => a.cmp(&b),
+            _ => compare_fractions((self.num, self.den), (other.num, other.den)),
+        }
+    }
+}
+
+/// Compares `a / b` with `c / d` for positive `b` and `d` without
+/// multiplying, by comparing their continued fraction expansions
+fn compare_fractions((mut a, mut b): (i128, i128), (mut c, mut d): (i128, i128)) -> Ordering {
+    let mut reversed = false;
+    loop {
+        let (q1, r1) = (a.div_euclid(b), a.rem_euclid(b));
+        let (q2, r2) = (c.div_euclid(d), c.rem_euclid(d));
+
+        let order = q1.cmp(&q2).then((r1 != 0).cmp(&(r2 != 0)));
+        if order != Ordering::Equal || r1 == 0 {
+            return if reversed { order.reverse() } else { order };
+        }
+
+        // r1 / b < r2 / d exactly when b / r1 > d / r2
+        (a, b, c, d) = (b, r1, d, r2);
+        reversed = !reversed;
+    }
+}
+impl Neg for Rational {
+    type Output = Self;
+    fn neg(self) -> Self::Output {
+        Self {
+            num: -self.num,
+            den: self.den,
+        }
+    }
+}
+impl Add for Rational {
+    type Output = Self;
+    fn add(self, rhs: Self) -> Self::Output {
+        self.checked_add(rhs).expect("rational overflow")
+    }
+}
+impl Sub for Rational {
+    type Output = Self;
+    fn sub(self, rhs: Self) -> Self::Output {
+        self.checked_sub(rhs).expect("rational overflow")
+    }
+}
+impl Mul for Rational {
+    type Output = Self;
+    fn mul(self, rhs: Self) -> Self::Output {
+        self.checked_mul(rhs).expect("rational overflow")
+    }
+}
+impl Div for Rational {
+    type Output = Self;
+    fn div(self, rhs: Self) -> Self::Output {
+        self.checked_div(rhs).expect("division by zero")
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Solution {
+    Unique(Vec<Rational>),
+    /// Underdetermined. `particular` has every free variable set to zero
+    Infinite {
+        particular: Vec<Rational>,
+        free: Vec<usize>,
+    },
+    Inconsistent,
+}
+impl Solution {
+    /// The unique solution, if it exists and is all integers
+    pub fn integers(&self) -> Option<Vec<i128>> {
+        match self {
+            Self::Unique(x) => x.iter().map(Rational::to_integer).collect(),
+            _ => None,
+        }
+    }
+}
+
+fn to_rows<T, R>(rows: &[R]) -> Vec<Vec<Rational>>
+where
+    T: Copy + Into<Rational>,
+    R: AsRef<[T]>,
+{
+    rows.iter()
+        .map(|r| r.as_ref().iter().map(|x| (*x).into()).collect())
+        .collect()
+}
+
+/// Solves `a · x = b` by Gauss-Jordan elimination. `a` may be non-square
+pub fn solve<T, R>(a: &[R], b: &[T]) -> Solution
+where
+    T: Copy + Into<Rational>,
+    R: AsRef<[T]>,
+{
+    assert_eq!(a.len(), b.len(), "one right hand side per equation");
+
+    let mut rows = to_rows(a);
+    let unknowns = rows.first().map_or(0, |r| r.len());
+    for (row, rhs) in rows.iter_mut().zip(b) {
+        assert_eq!(row.len(), unknowns, "ragged matrix");
+        row.push((*rhs).into());
+    }
+
+    let mut pivots = vec![];
+    for col in 0..unknowns {
+        let r = pivots.len();
+        let Some(pivot) = (r..rows.len()).find(|i| !rows[*i][col].is_zero()) else {
+            continue;
+        };
+        rows.swap(r, pivot);
+
+        let scale = rows[r][col];
+        rows[r].iter_mut().for_each(|x| *x = *x / scale);
+
+        let pivot_row = rows[r].clone();
+        for (i, row) in rows.iter_mut().enumerate() {
+            let factor = row[col];
+            if i == r || factor.is_zero() {
+                continue;
+            }
+            for (x, p) in row[col..].iter_mut().zip(&pivot_row[col..]) {
+                *x = *x - factor * *p;
+            }
+        }
+
+        pivots.push(col);
+    }
+
+    if rows[pivots.len()..].iter().any(|r| !r[unknowns].is_zero()) {
+        return Solution::Inconsistent;
+    }
+
+    let mut particular = vec![Rational::ZERO; unknowns];
+    for (r, col) in pivots.iter().enumerate() {
+        particular[*col] = rows[r][unknowns];
+    }
+
+    if pivots.len() == unknowns {
+        Solution::Unique(particular)
+    } else {
+        let free = (0..unknowns).filter(|c| !pivots.contains(c)).collect();
+        Solution::Infinite { particular, free }
+    }
+}
+
+/// [`solve`] for two equations in two unknowns by Cramer's rule, without
+/// allocating. `None` if `a` is singular, where [`solve`] tells the cases apart
+pub fn solve_2x2(a: [[i64; 2]; 2], b: [i64; 2]) -> Option<[Rational; 2]> {
+    let [[p, q], [r, s]] = a.map(|row| row.map(i128::from));
+    let [x, y] = b.map(i128::from);
+
+    // Products of two i64s and their differences fit in an i128
+    let det = p * s - q * r;
+    (det != 0).then(|| {
+        [
+            Rational::new(x * s - q * y, det),
+            Rational::new(p * y - x * r, det),
+        ]
+    })
+}
+
+pub fn determinant<T, R>(a: &[R]) -> Rational
+where
+    T: Copy + Into<Rational>,
+    R: AsRef<[T]>,
+{
+    let mut rows = to_rows(a);
+    let n = rows.len();
+    assert!(rows.iter().all(|r| r.len() == n), "matrix must be square");
+
+    let mut det = Rational::ONE;
+    for col in 0..n {
+        let Some(pivot) = (col..n).find(|i| !rows[*i][col].is_zero()) else {
+            return Rational::ZERO;
+        };
+        if pivot != col {
+            rows.swap(col, pivot);
+            det = -det;
+        }
+
+        det = det * rows[col][col];
+        let pivot_row = rows[col].clone();
+        for row in &mut rows[col + 1..] {
+            let factor = row[col] / pivot_row[col];
+            for (x, p) in row[col..].iter_mut().zip(&pivot_row[col..]) {
+                *x = *x - factor * *p;
+            }
+        }
+    }
+
+    det
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn r(num: i128, den: i128) -> Rational {
+        Rational::new(num, den)
+    }
+
+    #[test]
+    fn rational_arithmetic() {
+        assert_eq!(r(2, -4), r(-1, 2));
+        assert_eq!(r(1, 2) + r(1, 3), r(5, 6));
+        assert_eq!(r(1, 2) - r(3, 4), r(-1, 4));
+        assert_eq!(r(2, 3) * r(9, 4), r(3, 2));
+        assert_eq!(r(2, 3) / r(-4, 9), r(-3, 2));
+        assert!(r(1, 3) < r(1, 2));
+        assert_eq!(r(6, 3).to_integer(), Some(2));
+        assert_eq!(r(7, 3).to_integer(), None);
+        assert_eq!(r(-7, 3).to_string(), "-7/3");
+
+        assert_eq!(Rational::checked_new(1, 0), None);
+        assert_eq!(Rational::ONE.checked_div(Rational::ZERO), None);
+        assert_eq!(Rational::from(i128::MAX).checked_add(Rational::ONE), None);
+    }
+
+    #[test]
+    fn large_comparisons() {
+        // Cross products overflow i128
+        let big = i128::MAX / 3;
+        assert!(r(big, big - 1) < r(big - 1, big - 2));
+        assert!(r(big - 1, big) < r(big, big + 1));
+        assert_eq!(r(big, 7).cmp(&r(big, 7)), Ordering::Equal);
+        assert!(r(-big, 7) < r(big, 11));
+        assert!(r(-big, big - 1) > r(-big + 1, big - 2));
+        assert!(r(1, big) > Rational::ZERO);
+
+        for (a, b, c, d) in [(3, 4, 6, 8), (-1, 3, -1, 2), (7, 1, 13, 2), (0, 1, -5, 9)] {
+            assert_eq!(
+                compare_fractions((a, b), (c, d)),
+                (a * d).cmp(&(c * b)),
+                "{a}/{b} vs {c}/{d}"
+            );
+        }
+    }
+
+    #[test]
+    fn unique() {
+        let solution = solve(&[[94, 22], [34, 67]], &[8400, 5400]);
+        assert_eq!(solution.integers(), Some(vec![80, 40]));
+
+        let solution = solve(&[[26, 67], [66, 21]], &[12748, 12176]);
+        assert!(matches!(solution, Solution::Unique(_)));
+        assert_eq!(solution.integers(), None);
+
+        // Large right hand sides stay exact
+        let offset = 10_000_000_000_000_i64;
+        let solution = solve(&[[26, 67], [66, 21]], &[12748 + offset, 12176 + offset]);
+        assert_eq!(solution.integers(), Some(vec![118679050709, 103199174542]));
+    }
+
+    #[test]
+    fn singular() {
+        assert_eq!(
+            solve(&[[1, 2], [2, 4]], &[3, 6]),
+            Solution::Infinite {
+                particular: vec![r(3, 1), Rational::ZERO],
+                free: vec![1],
+            }
+        );
+        assert_eq!(solve(&[[1, 2], [2, 4]], &[3, 7]), Solution::Inconsistent);
+    }
+
+    #[test]
+    fn cramer() {
+        for (a, b) in [
+            ([[94, 22], [34, 67]], [8400, 5400]),
+            ([[26, 67], [66, 21]], [12748, 12176]),
+            ([[0, 1], [1, 0]], [-3, 5]),
+        ] {
+            let expected = match solve(&a, &b) {
+                Solution::Unique(x) => x,
+                x => panic!("{x:?}"),
+            };
+            assert_eq!(solve_2x2(a, b).map(Vec::from), Some(expected));
+        }
+
+        let max = i64::MAX;
+        assert_eq!(
+            solve_2x2([[max, -max], [max, max]], [max, max]),
+            Some([Rational::ONE, Rational::ZERO])
+        );
+        assert_eq!(solve_2x2([[1, 2], [2, 4]], [3, 6]), None);
+    }
+
+    #[test]
+    fn determinants() {
+        assert_eq!(determinant(&[[94, 22], [34, 67]]), r(94 * 67 - 22 * 34, 1));
+        assert_eq!(determinant(&[[0, 1, 2], [1, 0, 3], [4, -3, 8]]), r(-2, 1));
+        assert_eq!(determinant(&[[1, 2], [2, 4]]), Rational::ZERO);
+    }
+}
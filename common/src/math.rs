@@ -0,0 +1,258 @@
+/// Number of decimal digits in `x`, with `digits(0) == 1`
+pub fn digits(x: u64) -> u32 {
+    x.checked_ilog10().map_or(1, |d| d + 1)
+}
+
+pub fn pow10(n: u32) -> u64 {
+    10u64.pow(n)
+}
+
+pub fn checked_pow10(n: u32) -> Option<u64> {
+    10u64.checked_pow(n)
+}
+
+/// Splits `x` into the digits before and the last `low_digits` digits
+pub fn split_digits(x: u64, low_digits: u32) -> (u64, u64) {
+    match checked_pow10(low_digits) {
+        Some(power) => (x / power, x % power),
+        None => (0, x),
+    }
+}
+
+/// Writes the digits of `b` after those of `a`, e.g. `concat(12, 345) == 12345`
+pub fn concat(a: u64, b: u64) -> u64 {
+    checked_concat(a, b).expect("concat overflowed")
+}
+
+pub fn checked_concat(a: u64, b: u64) -> Option<u64> {
+    a.checked_mul(checked_pow10(digits(b))?)?.checked_add(b)
+}
+
+/// Non-negative greatest common divisor, with `gcd(0, 0) == 0`
+pub fn gcd(a: i64, b: i64) -> i64 {
+    checked_gcd(a, b).expect("gcd overflowed")
+}
+
+/// `None` when the divisor is 2^63, as for `gcd(i64::MIN, 0)`
+pub fn checked_gcd(a: i64, b: i64) -> Option<i64> {
+    let (mut a, mut b) = (a.unsigned_abs(), b.unsigned_abs());
+    while b != 0 {
+        (a, b) = (b, a % b);
+    }
+    i64::try_from(a).ok()
+}
+
+pub fn lcm(a: i64, b: i64) -> i64 {
+    checked_lcm(a, b).expect("lcm overflowed")
+}
+
+pub fn checked_lcm(a: i64, b: i64) -> Option<i64> {
+    if a == 0 || b == 0 {
+        return Some(0);
+    }
+    (a / checked_gcd(a, b)?).checked_mul(b)?.checked_abs()
+}
+
+/// Returns `(g, x, y)` with `a * x + b * y == g == gcd(a, b)`
+pub fn extended_gcd(a: i64, b: i64) -> (i64, i64, i64) {
+    checked_extended_gcd(a, b).expect("extended_gcd overflowed")
+}
+
+pub fn checked_extended_gcd(a: i64, b: i64) -> Option<(i64, i64, i64)> {
+    // Bezout coefficients are bounded by the inputs, so i128 can't overflow
+    let (mut old_r, mut r) = (a as i128, b as i128);
+    let (mut old_x, mut x) = (1i128, 0i128);
+    let (mut old_y, mut y) = (0i128, 1i128);
+
+    while r != 0 {
+        let q = old_r / r;
+        (old_r, r) = (r, old_r - q * r);
+        (old_x, x) = (x, old_x - q * x);
+        (old_y, y) = (y, old_y - q * y);
+    }
+
+    if old_r < 0 {
+        (old_r, old_x, old_y) = (-old_r, -old_x, -old_y);
+    }
+
+    Some((
+        i64::try_from(old_r).ok()?,
+        i64::try_from(old_x).ok()?,
+        i64::try_from(old_y).ok()?,
+    ))
+}
+
+/// `base ^ exp mod modulus`, in `0..modulus`
+pub fn mod_pow(base: i64, exp: u64, modulus: i64) -> i64 {
+    checked_mod_pow(base, exp, modulus).expect("modulus must be positive")
+}
+
+/// `None` unless `modulus` is positive
+pub fn checked_mod_pow(base: i64, mut exp: u64, modulus: i64) -> Option<i64> {
+    if modulus <= 0 {
+        return None;
+    }
+
+    let m = modulus as i128;
+    let mut base = (base as i128).rem_euclid(m);
+    let mut result = 1 % m;
+
+    while exp > 0 {
+        if exp & 1 == 1 {
+            result = result * base % m;
+        }
+        base = base * base % m;
+        exp >>= 1;
+    }
+
+    Some(result as i64)
+}
+
+/// `x` in `0..modulus` with `a * x ≡ 1`, if `a` and `modulus` are coprime
+pub fn mod_inv(a: i64, modulus: i64) -> Option<i64> {
+    checked_mod_inv(a, modulus).expect("modulus must be positive")
+}
+
+/// Outer `None` unless `modulus` is positive
+pub fn checked_mod_inv(a: i64, modulus: i64) -> Option<Option<i64>> {
+    if modulus <= 0 {
+        return None;
+    }
+
+    // Reducing first keeps the coefficients below `modulus`
+    let (g, x, _) = checked_extended_gcd(a.rem_euclid(modulus), modulus)?;
+    Some((g == 1).then(|| x.rem_euclid(modulus)))
+}
+
+/// Solves `x ≡ r (mod m)` for every `(r, m)`, returning `(x, lcm of m)`.
+/// Moduli need not be coprime. `None` if there is no solution
+pub fn crt(congruences: &[(i64, i64)]) -> Option<(i64, i64)> {
+    checked_crt(congruences).expect("crt overflowed")
+}
+
+/// Outer `None` if a modulus isn't positive or their lcm overflows
+pub fn checked_crt(congruences: &[(i64, i64)]) -> Option<Option<(i64, i64)>> {
+    let (mut r1, mut m1) = (0i64, 1i64);
+
+    for &(r2, m2) in congruences {
+        if m2 <= 0 {
+            return None;
+        }
+
+        let (g, p, _) = checked_extended_gcd(m1, m2)?;
+        let difference = r2 as i128 - r1 as i128;
+        if difference % g as i128 != 0 {
+            return Some(None);
+        }
+
+        // Reducing before multiplying keeps every product below 2^126
+        let m = checked_lcm(m1, m2)?;
+        let modulus = (m2 / g) as i128;
+        let step = (difference / g as i128).rem_euclid(modulus) * p as i128 % modulus;
+        let x = (r1 as i128 + m1 as i128 * step).rem_euclid(m as i128);
+
+        (r1, m1) = (x as i64, m);
+    }
+
+    Some(Some((r1, m1)))
+}
+
+/// Largest `r` with `r * r <= x`
+pub fn isqrt(x: u64) -> u64 {
+    x.isqrt()
+}
+
+/// `None` for negative `x`
+pub fn checked_isqrt(x: i64) -> Option<i64> {
+    x.checked_isqrt()
+}
+
+pub fn is_square(x: u64) -> bool {
+    let r = isqrt(x);
+    r * r == x
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn digit_ops() {
+        assert_eq!(digits(0), 1);
+        assert_eq!(digits(9), 1);
+        assert_eq!(digits(10), 2);
+        assert_eq!(digits(999_999_999_999_999_999), 18);
+        assert_eq!(digits(1_000_000_000_000_000_000), 19);
+        assert_eq!(digits(u64::MAX), 20);
+
+        assert_eq!(split_digits(123456, 2), (1234, 56));
+        assert_eq!(split_digits(42, 25), (0, 42));
+
+        assert_eq!(concat(15, 3), 153);
+        assert_eq!(concat(1, 0), 10);
+        assert_eq!(checked_concat(u64::MAX / 10, 9), None);
+    }
+
+    #[test]
+    fn gcd_lcm() {
+        assert_eq!(gcd(12, -18), 6);
+        assert_eq!(gcd(0, 0), 0);
+        assert_eq!(lcm(4, 6), 12);
+        assert_eq!(lcm(101, 103), 10403);
+        assert_eq!(checked_lcm(i64::MAX, i64::MAX - 1), None);
+        assert_eq!(checked_lcm(i64::MIN, 1), None);
+        assert_eq!(checked_gcd(i64::MIN, 0), None);
+        assert_eq!(checked_gcd(i64::MIN, 6), Some(2));
+        assert_eq!(checked_extended_gcd(i64::MIN, i64::MIN), None);
+        assert_eq!(checked_extended_gcd(i64::MIN, i64::MAX), Some((1, -1, -1)));
+
+        for (a, b) in [(240, 46), (-7, 3), (0, 5), (17, 0)] {
+            let (g, x, y) = extended_gcd(a, b);
+            assert_eq!(g, gcd(a, b));
+            assert_eq!(a * x + b * y, g);
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "modulus must be positive")]
+    fn mod_inv_of_non_positive_modulus() {
+        mod_inv(3, 0);
+    }
+
+    #[test]
+    fn modular() {
+        assert_eq!(mod_pow(2, 10, 1000), 24);
+        assert_eq!(mod_pow(-2, 3, 5), 2);
+        assert_eq!(mod_pow(i64::MAX, u64::MAX, 1_000_000_007), 841_470_462);
+        assert_eq!(mod_inv(3, 11), Some(4));
+        assert_eq!(mod_inv(6, 9), None);
+        assert_eq!(mod_inv(i64::MIN, 3), Some(1));
+        assert_eq!(mod_inv(-1, i64::MAX), Some(i64::MAX - 1));
+
+        assert_eq!(crt(&[(2, 3), (3, 5), (2, 7)]), Some((23, 105)));
+        assert_eq!(crt(&[(1, 4), (3, 6)]), Some((9, 12)));
+        assert_eq!(crt(&[(1, 4), (2, 6)]), None);
+        assert_eq!(crt(&[]), Some((0, 1)));
+
+        assert_eq!(checked_mod_pow(2, 3, 0), None);
+        assert_eq!(checked_mod_inv(3, -11), None);
+        assert_eq!(checked_mod_inv(6, 9), Some(None));
+        assert_eq!(checked_crt(&[(1, 0)]), None);
+        assert_eq!(checked_crt(&[(0, i64::MAX), (0, i64::MAX - 1)]), None);
+        assert_eq!(
+            checked_crt(&[(i64::MIN, 1 << 40), (i64::MAX, 3)]),
+            Some(Some((1 << 40, 3 << 40)))
+        );
+    }
+
+    #[test]
+    fn roots() {
+        assert_eq!(isqrt(24), 4);
+        assert_eq!(isqrt(25), 5);
+        assert_eq!(isqrt(u64::MAX), u32::MAX as u64);
+        assert_eq!(checked_isqrt(i64::MAX), Some(3_037_000_499));
+        assert_eq!(checked_isqrt(-1), None);
+        assert!(is_square(144));
+        assert!(!is_square(145));
+    }
+}
@@ -0,0 +1,272 @@
+use crate::{parse, Args};
+use std::{
+    collections::HashMap,
+    fmt::{Debug, Display},
+    ops::RangeBounds,
+    str::FromStr,
+};
+
+type Check = Box<dyn Fn(&str) -> Result<(), String>>;
+
+/// Options every runner understands, accepted alongside any schema
+const RUNNER_OPTIONS: [&str; 3] = ["config", "input", "profile"];
+
+struct Param {
+    name: &'static str,
+    default: String,
+    help: &'static str,
+    check: Check,
+}
+
+/// Named puzzle parameters with defaults. Values come from the defaults, then
+/// a `--config <file>` of `name = value` lines, then `--name value` flags
+#[derive(Default)]
+pub struct Schema {
+    params: Vec<Param>,
+    /// Other options the day reads itself, with their descriptions
+    options: Vec<(&'static str, &'static str)>,
+}
+impl Schema {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn param<T>(self, name: &'static str, default: T, help: &'static str) -> Self
+    where
+        T: FromStr + Display + 'static,
+        T::Err: Debug,
+    {
+        self.checked(name, default, help, |_| Ok(()))
+    }
+
+    /// Parameter that must lie within `range`
+    pub fn bounded<T>(
+        self,
+        name: &'static str,
+        default: T,
+        range: impl RangeBounds<T> + Debug + 'static,
+        help: &'static str,
+    ) -> Self
+    where
+        T: FromStr + Display + PartialOrd + 'static,
+        T::Err: Debug,
+    {
+        self.checked(name, default, help, move |x| {
+            if range.contains(x) {
+                Ok(())
+            } else {
+                Err(format!("{x} is outside {range:?}"))
+            }
+        })
+    }
+
+    /// Option read directly from the arguments, such as a flag for extra
+    /// output, so it isn't rejected as unknown
+    pub fn option(mut self, name: &'static str, help: &'static str) -> Self {
+        assert!(
+            self.find(name).is_none() && self.options.iter().all(|(x, _)| *x != name),
+            "option {name} declared twice"
+        );
+
+        self.options.push((name, help));
+        self
+    }
+
+    fn checked<T>(
+        mut self,
+        name: &'static str,
+        default: T,
+        help: &'static str,
+        check: impl Fn(&T) -> Result<(), String> + 'static,
+    ) -> Self
+    where
+        T: FromStr + Display + 'static,
+        T::Err: Debug,
+    {
+        assert!(
+            self.find(name).is_none() && self.options.iter().all(|(x, _)| *x != name),
+            "parameter {name} declared twice"
+        );
+
+        let check = move |value: &str| {
+            let x = value.parse::<T>().map_err(|e| format!("{e:?}"))?;
+            check(&x)
+        };
+        let default = default.to_string();
+        check(&default).unwrap_or_else(|e| panic!("Invalid default for {name}: {e}"));
+
+        self.params.push(Param {
+            name,
+            default,
+            help,
+            check: Box::new(check),
+        });
+        self
+    }
+
+    fn find(&self, name: &str) -> Option<&Param> {
+        self.params.iter().find(|p| p.name == name)
+    }
+
+    /// Resolves every parameter from `args`, reading the file named by `--config`
+    pub fn load(&self, args: &Args) -> Result<Params, String> {
+        let config = args
+            .get::<String>("config")
+            .map(|path| {
+                std::fs::read_to_string(&path).map_err(|e| format!("Can't read {path}: {e}"))
+            })
+            .transpose()?;
+
+        self.resolve(config.as_deref(), args)
+    }
+
+    /// Resolves every parameter from an optional config file's contents and
+    /// `args`, which may only hold known options
+    pub fn resolve(&self, config: Option<&str>, args: &Args) -> Result<Params, String> {
+        let mut unknown = args
+            .keys()
+            .filter(|key| {
+                self.find(key).is_none()
+                    && self.options.iter().all(|(x, _)| x != key)
+                    && !RUNNER_OPTIONS.contains(key)
+            })
+            .collect::<Vec<_>>();
+        unknown.sort();
+        if let Some(key) = unknown.first() {
+            return Err(format!("unknown option --{key}"));
+        }
+
+        let mut values: HashMap<_, _> = self
+            .params
+            .iter()
+            .map(|p| (p.name, p.default.clone()))
+            .collect();
+
+        let lines = config.unwrap_or_default().lines().enumerate();
+        for (i, line) in lines {
+            let line = line.split('#').next().unwrap().trim();
+            if line.is_empty() {
+                continue;
+            }
+
+            let Some((name, value)) = parse::key_value(line, "=") else {
+                return Err(format!("config line {}: expected name = value", i + 1));
+            };
+            let param = self
+                .find(name)
+                .ok_or_else(|| format!("config line {}: unknown parameter {name:?}", i + 1))?;
+            values.insert(param.name, value.to_string());
+        }
+
+        for p in &self.params {
+            if let Some(value) = args.get::<String>(p.name) {
+                values.insert(p.name, value);
+            } else if args.flag(p.name) {
+                return Err(format!("--{} needs a value", p.name));
+            }
+
+            (p.check)(&values[p.name]).map_err(|e| format!("--{}: {e}", p.name))?;
+        }
+
+        Ok(Params { values })
+    }
+
+    /// One line per parameter with its default and description, then the
+    /// other options
+    pub fn help(&self) -> String {
+        let params = self
+            .params
+            .iter()
+            .map(|p| format!("  --{} <{}>  {}\n", p.name, p.default, p.help));
+        let options = self
+            .options
+            .iter()
+            .map(|(name, help)| format!("  --{name}  {help}\n"));
+        params.chain(options).collect()
+    }
+}
+
+/// Validated values of a [`Schema`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Params {
+    values: HashMap<&'static str, String>,
+}
+impl Params {
+    /// Panics if `name` isn't in the schema or `T` isn't the type it was
+    /// declared with, as values were already checked when resolving
+    pub fn get<T>(&self, name: &str) -> T
+    where
+        T: FromStr,
+        T::Err: Debug,
+    {
+        let value = self
+            .values
+            .get(name)
+            .unwrap_or_else(|| panic!("Unknown parameter {name}"));
+        value.parse().unwrap_or_else(|e| {
+            panic!(
+                "Parameter {name} isn't a {}: {e:?}",
+                std::any::type_name::<T>()
+            )
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn schema() -> Schema {
+        Schema::new()
+            .bounded("width", 101, 1.., "grid width")
+            .param("name", String::from("day"), "label")
+            .param("scale", 0.5, "scale factor")
+            .option("verbose", "print more")
+    }
+
+    #[test]
+    fn defaults_and_overrides() {
+        let params = schema().resolve(None, &Args::default()).unwrap();
+        assert_eq!(params.get::<i32>("width"), 101);
+        assert_eq!(params.get::<String>("name"), "day");
+
+        let config = "# example\nwidth = 11\n\nscale = 2 # doubled\n";
+        let args = Args::parse(["--scale", "3", "--profile", "--verbose"]);
+        let params = schema().resolve(Some(config), &args).unwrap();
+        assert_eq!(params.get::<i32>("width"), 11);
+        assert_eq!(params.get::<f64>("scale"), 3.0);
+    }
+
+    #[test]
+    fn validation() {
+        let check = |config: Option<&str>, args: &[&str]| {
+            schema()
+                .resolve(config, &Args::parse(args.iter().copied()))
+                .unwrap_err()
+        };
+
+        assert_eq!(check(None, &["--width", "0"]), "--width: 0 is outside 1..");
+        assert!(check(None, &["--width", "x"]).starts_with("--width: "));
+        assert_eq!(check(None, &["--width"]), "--width needs a value");
+        assert_eq!(
+            check(Some("height = 3"), &[]),
+            "config line 1: unknown parameter \"height\""
+        );
+        assert_eq!(
+            check(Some("\nwidth"), &[]),
+            "config line 2: expected name = value"
+        );
+        assert_eq!(
+            check(None, &["--widht", "11", "--height", "3"]),
+            "unknown option --height"
+        );
+        assert!(schema().help().contains("--width <101>  grid width"));
+        assert!(schema().help().contains("--verbose  print more"));
+    }
+
+    #[test]
+    #[should_panic(expected = "Invalid default")]
+    fn bad_default() {
+        Schema::new().bounded("size", 0, 1..10, "size");
+    }
+}
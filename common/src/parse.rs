@@ -0,0 +1,406 @@
+use std::{fmt::Display, str::FromStr};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+    /// Byte offset into the parsed input
+    pub offset: usize,
+    /// 1-based
+    pub line: usize,
+    /// 1-based, in characters
+    pub column: usize,
+    pub expected: String,
+}
+impl Display for ParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "line {}, column {}: expected {}",
+            self.line, self.column, self.expected
+        )
+    }
+}
+impl std::error::Error for ParseError {}
+
+pub type ParseResult<T> = Result<T, ParseError>;
+
+/// Cursor over an input string. Failed parsers may have consumed input, use
+/// [`Parser::attempt`] to backtrack
+#[derive(Debug, Clone, Copy)]
+pub struct Parser<'a> {
+    input: &'a str,
+    offset: usize,
+}
+impl<'a> Parser<'a> {
+    pub fn new(input: &'a str) -> Self {
+        Self { input, offset: 0 }
+    }
+
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+
+    /// Input not yet consumed
+    pub fn rest(&self) -> &'a str {
+        &self.input[self.offset..]
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.offset == self.input.len()
+    }
+
+    /// Error at the current position
+    pub fn error(&self, expected: impl Into<String>) -> ParseError {
+        let before = &self.input[..self.offset];
+        let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+
+        ParseError {
+            offset: self.offset,
+            line: before.matches('\n').count() + 1,
+            column: before[line_start..].chars().count() + 1,
+            expected: expected.into(),
+        }
+    }
+
+    fn advance(&mut self, len: usize) -> &'a str {
+        let taken = &self.rest()[..len];
+        self.offset += len;
+        taken
+    }
+
+    /// The next `len` bytes, which must end on a character boundary
+    pub fn take(&mut self, len: usize) -> &'a str {
+        self.advance(len)
+    }
+
+    pub fn tag(&mut self, tag: &str) -> ParseResult<()> {
+        if self.try_tag(tag) {
+            Ok(())
+        } else {
+            Err(self.error(format!("{tag:?}")))
+        }
+    }
+
+    /// Consumes `tag` if the input starts with it
+    pub fn try_tag(&mut self, tag: &str) -> bool {
+        let matches = self.rest().starts_with(tag);
+        if matches {
+            self.advance(tag.len());
+        }
+        matches
+    }
+
+    pub fn next_char(&mut self) -> Option<char> {
+        let c = self.rest().chars().next()?;
+        self.advance(c.len_utf8());
+        Some(c)
+    }
+
+    pub fn take_while(&mut self, predicate: impl Fn(char) -> bool) -> &'a str {
+        let len = self
+            .rest()
+            .find(|c| !predicate(c))
+            .unwrap_or(self.rest().len());
+        self.advance(len)
+    }
+
+    /// Everything up to `delimiter` or the end, without consuming the delimiter
+    pub fn until(&mut self, delimiter: &str) -> &'a str {
+        let len = self.rest().find(delimiter).unwrap_or(self.rest().len());
+        self.advance(len)
+    }
+
+    /// Integer with an optional `+` or `-` sign
+    pub fn int<T: FromStr>(&mut self) -> ParseResult<T> {
+        let sign = self.rest().starts_with(['-', '+']) as usize;
+        self.number(sign)
+    }
+
+    /// Integer without a sign
+    pub fn uint<T: FromStr>(&mut self) -> ParseResult<T> {
+        self.number(0)
+    }
+
+    fn number<T: FromStr>(&mut self, sign: usize) -> ParseResult<T> {
+        let start = *self;
+        let digits = self.rest()[sign..]
+            .find(|c: char| !c.is_ascii_digit())
+            .unwrap_or(self.rest().len() - sign);
+
+        if digits == 0 {
+            return Err(self.error("integer"));
+        }
+
+        let text = self.advance(sign + digits);
+        text.trim_start_matches('+').parse().map_err(|_| {
+            *self = start;
+            self.error("integer in range")
+        })
+    }
+
+    /// Non-empty run of alphanumerics or `_`
+    pub fn word(&mut self) -> ParseResult<&'a str> {
+        match self.take_while(|c| c.is_alphanumeric() || c == '_') {
+            "" => Err(self.error("word")),
+            word => Ok(word),
+        }
+    }
+
+    /// Skips spaces and tabs
+    pub fn spaces(&mut self) {
+        self.take_while(|c| c == ' ' || c == '\t');
+    }
+
+    /// Skips all whitespace, including newlines
+    pub fn whitespace(&mut self) {
+        self.take_while(char::is_whitespace);
+    }
+
+    pub fn newline(&mut self) -> ParseResult<()> {
+        if self.try_tag("\n") || self.try_tag("\r\n") {
+            Ok(())
+        } else {
+            Err(self.error("end of line"))
+        }
+    }
+
+    /// Rest of the current line, consuming the line break
+    pub fn line(&mut self) -> &'a str {
+        let line = self.until("\n");
+        self.try_tag("\n");
+        line.strip_suffix('\r').unwrap_or(line)
+    }
+
+    pub fn end(&self) -> ParseResult<()> {
+        if self.is_done() {
+            Ok(())
+        } else {
+            Err(self.error("end of input"))
+        }
+    }
+
+    /// Runs `f`, rewinding if it fails
+    pub fn attempt<T>(&mut self, f: impl FnOnce(&mut Self) -> ParseResult<T>) -> Option<T> {
+        let start = *self;
+        f(self).inspect_err(|_| *self = start).ok()
+    }
+
+    /// One or more `item`s separated by `separator`
+    pub fn separated<T>(
+        &mut self,
+        separator: &str,
+        mut item: impl FnMut(&mut Self) -> ParseResult<T>,
+    ) -> ParseResult<Vec<T>> {
+        let mut items = vec![item(self)?];
+        loop {
+            let start = *self;
+            if !self.try_tag(separator) {
+                break;
+            }
+            match item(self) {
+                Ok(x) => items.push(x),
+                Err(_) => {
+                    // Leave a trailing separator for the caller
+                    *self = start;
+                    break;
+                }
+            }
+        }
+        Ok(items)
+    }
+
+    /// `item`s separated by any whitespace, until the end of input
+    pub fn many<T>(
+        &mut self,
+        mut item: impl FnMut(&mut Self) -> ParseResult<T>,
+    ) -> ParseResult<Vec<T>> {
+        let mut items = vec![];
+        self.whitespace();
+        while !self.is_done() {
+            items.push(item(self)?);
+            self.whitespace();
+        }
+        Ok(items)
+    }
+}
+
+/// Parses all of `input` with `f`, allowing surrounding whitespace
+pub fn parse_all<'a, T>(
+    input: &'a str,
+    f: impl FnOnce(&mut Parser<'a>) -> ParseResult<T>,
+) -> ParseResult<T> {
+    let mut p = Parser::new(input);
+    p.whitespace();
+    let x = f(&mut p)?;
+    p.whitespace();
+    p.end()?;
+    Ok(x)
+}
+
+/// Parses every non-blank line with `f`, which must consume the whole line.
+/// Error positions are relative to `input`
+pub fn lines_with<'a, T>(
+    input: &'a str,
+    mut f: impl FnMut(&mut Parser<'a>) -> ParseResult<T>,
+) -> ParseResult<Vec<T>> {
+    let mut p = Parser::new(input);
+    let mut items = vec![];
+
+    loop {
+        p.whitespace();
+        if p.is_done() {
+            return Ok(items);
+        }
+
+        items.push(f(&mut p)?);
+        p.spaces();
+        if !p.is_done() {
+            p.newline()?;
+        }
+    }
+}
+
+/// Blocks of lines separated by blank lines, trimmed, skipping empty blocks
+pub fn sections(input: &str) -> Vec<&str> {
+    let mut sections = vec![];
+    let mut start = None;
+    let mut end = 0;
+    let mut offset = 0;
+
+    for line in input.split_inclusive('\n') {
+        if line.trim().is_empty() {
+            if let Some(s) = start.take() {
+                sections.push(input[s..end].trim());
+            }
+        } else {
+            start.get_or_insert(offset);
+            end = offset + line.len();
+        }
+        offset += line.len();
+    }
+    if let Some(s) = start {
+        sections.push(input[s..end].trim());
+    }
+
+    sections
+}
+
+/// Splits `key<separator>value`, trimming both sides
+pub fn key_value<'a>(line: &'a str, separator: &str) -> Option<(&'a str, &'a str)> {
+    let (key, value) = line.split_once(separator)?;
+    Some((key.trim(), value.trim()))
+}
+
+/// Every integer in `line`. A `-` directly before digits is a sign unless it
+/// follows a digit, so `x=-3` gives -3 but `1-3` gives 1 and 3. Fails on the
+/// first integer that doesn't fit in `T`
+pub fn numbers_in<T: FromStr>(line: &str) -> ParseResult<Vec<T>> {
+    let bytes = line.as_bytes();
+    let mut numbers = vec![];
+    let mut i = 0;
+
+    while i < bytes.len() {
+        if !bytes[i].is_ascii_digit() {
+            i += 1;
+            continue;
+        }
+
+        let negative = i > 0 && bytes[i - 1] == b'-' && (i < 2 || !bytes[i - 2].is_ascii_digit());
+        let start = if negative { i - 1 } else { i };
+        while i < bytes.len() && bytes[i].is_ascii_digit() {
+            i += 1;
+        }
+
+        match line[start..i].parse() {
+            Ok(x) => numbers.push(x),
+            Err(_) => {
+                let mut p = Parser::new(line);
+                p.take(start);
+                return Err(p.error("integer in range"));
+            }
+        }
+    }
+
+    Ok(numbers)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn primitives() {
+        let mut p = Parser::new("Button A: X+94, Y-34\nrest");
+        p.tag("Button ").unwrap();
+        assert_eq!(p.word(), Ok("A"));
+        p.tag(": X").unwrap();
+        assert_eq!(p.int::<i64>(), Ok(94));
+        p.tag(", Y").unwrap();
+        assert_eq!(p.int::<i64>(), Ok(-34));
+        p.newline().unwrap();
+        assert_eq!(p.line(), "rest");
+        assert!(p.is_done());
+
+        let mut p = Parser::new("mul(2,4)");
+        assert_eq!(p.take(4), "mul(");
+        assert_eq!(p.rest(), "2,4)");
+
+        let mut p = Parser::new("-3");
+        assert!(p.uint::<u8>().is_err());
+        assert_eq!(p.next_char(), Some('-'));
+        assert_eq!(p.uint::<u8>(), Ok(3));
+
+        let mut p = Parser::new("300");
+        assert_eq!(p.int::<u8>().unwrap_err().expected, "integer in range");
+        assert_eq!(p.offset(), 0);
+    }
+
+    #[test]
+    fn combinators() {
+        let mut p = Parser::new("1,2,3, x");
+        assert_eq!(p.separated(",", |p| p.int::<u8>()), Ok(vec![1, 2, 3]));
+        assert_eq!(p.rest(), ", x");
+
+        let mut p = Parser::new("a1 b2");
+        assert_eq!(p.attempt(|p| p.int::<u8>()), None);
+        assert_eq!(p.offset(), 0);
+
+        let pairs = Parser::new("  a1 b2\n c3 ").many(|p| {
+            let name = p.take_while(|c| c.is_ascii_lowercase());
+            Ok((name, p.int::<u8>()?))
+        });
+        assert_eq!(pairs, Ok(vec![("a", 1), ("b", 2), ("c", 3)]));
+    }
+
+    #[test]
+    fn error_positions() {
+        let err = lines_with("x=1\n\nx=2\nx=y\n", |p| {
+            p.tag("x=")?;
+            p.int::<i32>()
+        })
+        .unwrap_err();
+
+        assert_eq!((err.line, err.column, err.offset), (4, 3, 11));
+        assert_eq!(err.to_string(), "line 4, column 3: expected integer");
+
+        let err = parse_all("1 2", |p| p.int::<i32>()).unwrap_err();
+        assert_eq!(err.expected, "end of input");
+    }
+
+    #[test]
+    fn helpers() {
+        assert_eq!(sections("\n a\nb\n\n  \n\nc\n"), ["a\nb", "c"]);
+        assert_eq!(
+            key_value("Register A: 729", ":"),
+            Some(("Register A", "729"))
+        );
+        assert_eq!(numbers_in::<i64>("p=0,4 v=3,-3"), Ok(vec![0, 4, 3, -3]));
+        assert_eq!(numbers_in::<i64>("1-3 x-7"), Ok(vec![1, 3, -7]));
+
+        let err = numbers_in::<i8>("1 -300 2").unwrap_err();
+        assert_eq!((err.offset, err.column), (2, 3));
+        assert_eq!(
+            err.to_string(),
+            "line 1, column 3: expected integer in range"
+        );
+    }
+}
@@ -0,0 +1,313 @@
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    cell::{Cell, RefCell},
+    fmt::Write,
+    sync::{
+        atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering::Relaxed},
+        Mutex, MutexGuard,
+    },
+    time::{Duration, Instant},
+};
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+static ALLOCATIONS: AtomicU64 = AtomicU64::new(0);
+static CURRENT_BYTES: AtomicUsize = AtomicUsize::new(0);
+static PEAK_BYTES: AtomicUsize = AtomicUsize::new(0);
+
+/// Global allocator that counts allocations and tracks peak live bytes.
+///
+/// Install with `#[global_allocator] static ALLOC: CountingAlloc = CountingAlloc;`
+#[derive(Debug)]
+pub struct CountingAlloc;
+
+fn record_alloc(size: usize) {
+    ALLOCATIONS.fetch_add(1, Relaxed);
+    let current = CURRENT_BYTES.fetch_add(size, Relaxed) + size;
+    PEAK_BYTES.fetch_max(current, Relaxed);
+}
+
+unsafe impl GlobalAlloc for CountingAlloc {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let ptr = System.alloc(layout);
+        if !ptr.is_null() {
+            record_alloc(layout.size());
+        }
+        ptr
+    }
+
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        let ptr = System.alloc_zeroed(layout);
+        if !ptr.is_null() {
+            record_alloc(layout.size());
+        }
+        ptr
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout);
+        CURRENT_BYTES.fetch_sub(layout.size(), Relaxed);
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        let new_ptr = System.realloc(ptr, layout, new_size);
+        if !new_ptr.is_null() {
+            CURRENT_BYTES.fetch_sub(layout.size(), Relaxed);
+            record_alloc(new_size);
+        }
+        new_ptr
+    }
+}
+
+/// Totals over every call of a span. Time is summed, so spans on parallel
+/// threads can add up to more than their parent. Allocation counts are
+/// process-wide, so they include other threads running at the same time
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct SpanStats {
+    pub name: &'static str,
+    pub calls: u64,
+    pub time: Duration,
+    pub allocations: u64,
+    /// Highest number of live bytes above the level at span entry
+    pub peak_bytes: usize,
+    children: Vec<usize>,
+}
+
+#[derive(Debug)]
+struct Frame {
+    node: usize,
+    start: Instant,
+    allocations: u64,
+    bytes: usize,
+    outer_peak: usize,
+}
+
+/// Span tree shared by every thread, with the root first
+static NODES: Mutex<Vec<SpanStats>> = Mutex::new(vec![]);
+
+thread_local! {
+    /// Open spans on this thread, innermost last
+    static STACK: RefCell<Vec<Frame>> = const { RefCell::new(vec![]) };
+    /// Where spans go when none are open on this thread
+    static BASE: Cell<usize> = const { Cell::new(0) };
+}
+
+fn nodes() -> MutexGuard<'static, Vec<SpanStats>> {
+    let mut nodes = NODES.lock().unwrap_or_else(|e| e.into_inner());
+    if nodes.is_empty() {
+        nodes.push(SpanStats::default());
+    }
+    nodes
+}
+
+pub fn enable() {
+    ENABLED.store(true, Relaxed);
+}
+
+pub fn is_enabled() -> bool {
+    ENABLED.load(Relaxed)
+}
+
+/// Times a named region until dropped. Spans nest, and repeated spans with
+/// the same parent are merged. Does nothing unless profiling is enabled
+#[must_use]
+#[derive(Debug)]
+pub struct Span(bool);
+
+/// A span to nest others under, possibly from another thread
+#[derive(Debug, Clone, Copy)]
+pub struct Parent(usize);
+
+/// The innermost span open on this thread
+pub fn current() -> Parent {
+    let open = STACK.with_borrow(|stack| stack.last().map(|f| f.node));
+    Parent(open.unwrap_or_else(|| BASE.get()))
+}
+
+/// Runs `func` with spans not inside another on this thread nested under
+/// `parent`, so work handed to other threads shows up where it was started
+pub fn within<Ret>(parent: Parent, func: impl FnOnce() -> Ret) -> Ret {
+    let outer = BASE.replace(parent.0);
+    let ret = func();
+    BASE.set(outer);
+    ret
+}
+
+pub fn span(name: &'static str) -> Span {
+    if !is_enabled() {
+        return Span(false);
+    }
+
+    let Parent(parent) = current();
+    let node = {
+        let mut nodes = nodes();
+        let existing = nodes[parent]
+            .children
+            .iter()
+            .copied()
+            .find(|i| nodes[*i].name == name);
+
+        existing.unwrap_or_else(|| {
+            nodes.push(SpanStats {
+                name,
+                ..Default::default()
+            });
+            let i = nodes.len() - 1;
+            nodes[parent].children.push(i);
+            i
+        })
+    };
+
+    let bytes = CURRENT_BYTES.load(Relaxed);
+    let outer_peak = PEAK_BYTES.swap(bytes, Relaxed);
+
+    STACK.with_borrow_mut(|stack| {
+        stack.push(Frame {
+            node,
+            start: Instant::now(),
+            allocations: ALLOCATIONS.load(Relaxed),
+            bytes,
+            outer_peak,
+        })
+    });
+
+    Span(true)
+}
+
+impl Drop for Span {
+    fn drop(&mut self) {
+        if !self.0 {
+            return;
+        }
+
+        let end = Instant::now();
+        let allocations = ALLOCATIONS.load(Relaxed);
+        let peak = PEAK_BYTES.load(Relaxed);
+
+        let frame = STACK.with_borrow_mut(|stack| stack.pop().unwrap());
+        let mut nodes = nodes();
+        let node = &mut nodes[frame.node];
+
+        node.calls += 1;
+        node.time += end - frame.start;
+        node.allocations += allocations.saturating_sub(frame.allocations);
+        node.peak_bytes = node.peak_bytes.max(peak.saturating_sub(frame.bytes));
+
+        PEAK_BYTES.fetch_max(frame.outer_peak, Relaxed);
+    }
+}
+
+/// Runs `func` inside a span
+pub fn spanned<Ret>(name: &'static str, func: impl FnOnce() -> Ret) -> Ret {
+    let _span = span(name);
+    func()
+}
+
+/// Recorded spans from every thread, depth first with their nesting depth
+pub fn spans() -> Vec<(usize, SpanStats)> {
+    fn visit(nodes: &[SpanStats], i: usize, depth: usize, out: &mut Vec<(usize, SpanStats)>) {
+        for child in &nodes[i].children {
+            out.push((depth, nodes[*child].clone()));
+            visit(nodes, *child, depth + 1, out);
+        }
+    }
+
+    let mut out = vec![];
+    visit(&nodes(), 0, 0, &mut out);
+    out
+}
+
+pub fn report() -> String {
+    let spans = spans();
+    let counting = ALLOCATIONS.load(Relaxed) > 0;
+
+    let mut out = format!("{:<32} {:>10} {:>14}", "span", "calls", "time");
+    if counting {
+        write!(out, " {:>12} {:>14}", "allocs", "peak bytes").unwrap();
+    }
+
+    for (depth, s) in spans {
+        let name = format!("{}{}", "  ".repeat(depth), s.name);
+        write!(
+            out,
+            "\n{name:<32} {:>10} {:>14}",
+            s.calls,
+            format!("{:.2?}", s.time)
+        )
+        .unwrap();
+        if counting {
+            write!(out, " {:>12} {:>14}", s.allocations, s.peak_bytes).unwrap();
+        }
+    }
+
+    out
+}
+
+/// Prints the report to stderr when dropped
+#[derive(Debug)]
+pub struct Session;
+impl Drop for Session {
+    fn drop(&mut self) {
+        eprintln!("{}", report());
+    }
+}
+
+/// Enables profiling for the rest of the run if `enabled` is set. Days opt
+/// in by calling this for `--profile`, which only day_6 does so far.
+/// Allocation columns also need a [`CountingAlloc`] installed, which day_6
+/// does behind its `count-alloc` feature
+pub fn session(enabled: bool) -> Option<Session> {
+    if enabled {
+        enable();
+        Some(Session)
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[global_allocator]
+    static ALLOC: CountingAlloc = CountingAlloc;
+
+    #[test]
+    fn nested_spans() {
+        enable();
+
+        spanned("outer", || {
+            for _ in 0..3 {
+                let _inner = span("inner");
+                std::hint::black_box(vec![0u8; 1000]);
+            }
+            let _other = span("other");
+        });
+
+        let spans = spans();
+        let names = spans.iter().map(|(d, s)| (*d, s.name)).collect::<Vec<_>>();
+        assert_eq!(names, [(0, "outer"), (1, "inner"), (1, "other")]);
+
+        let (_, inner) = &spans[1];
+        assert_eq!(inner.calls, 3);
+        assert!(inner.allocations >= 3);
+        assert!(inner.peak_bytes >= 1000);
+        assert!(spans[0].1.time >= inner.time);
+
+        assert!(report().lines().any(|l| l.starts_with("  inner ")));
+
+        // Spans on spawned threads nest where the work was started
+        spanned("parallel", || {
+            let parent = current();
+            std::thread::scope(|scope| {
+                for _ in 0..4 {
+                    scope.spawn(move || within(parent, || spanned("worker", || ())));
+                }
+            });
+        });
+        let spans = super::spans();
+        let names = spans.iter().map(|(d, s)| (*d, s.name)).collect::<Vec<_>>();
+        assert_eq!(names[3..], [(0, "parallel"), (1, "worker")]);
+        assert_eq!(spans[4].1.calls, 4);
+    }
+}
@@ -0,0 +1,368 @@
+use std::{cmp::Reverse, collections::BinaryHeap, fmt::Display, str::FromStr};
+
+/// Min-priority queue of dense node ids, as used by [`crate::search::dijkstra`].
+/// Queues may return stale entries for nodes that were pushed again with a
+/// lower cost, which callers must skip
+pub trait Frontier {
+    fn push(&mut self, cost: u64, node: usize);
+    fn pop(&mut self) -> Option<(u64, usize)>;
+    fn is_empty(&self) -> bool;
+}
+
+/// Binary heap that keeps every pushed entry (lazy deletion)
+#[derive(Debug, Default, Clone)]
+pub struct BinaryQueue(BinaryHeap<Reverse<(u64, usize)>>);
+impl BinaryQueue {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+impl Frontier for BinaryQueue {
+    fn push(&mut self, cost: u64, node: usize) {
+        self.0.push(Reverse((cost, node)));
+    }
+
+    fn pop(&mut self) -> Option<(u64, usize)> {
+        self.0.pop().map(|Reverse(x)| x)
+    }
+
+    fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+/// Binary min-heap over keys `0..capacity` with at most one entry per key, so
+/// priorities can be decreased in place
+#[derive(Debug, Clone)]
+pub struct IndexedHeap<P> {
+    heap: Vec<(P, usize)>,
+    /// Position of each key in `heap`, or `usize::MAX`
+    slots: Vec<usize>,
+}
+impl<P: Ord + Copy> IndexedHeap<P> {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            heap: vec![],
+            slots: vec![usize::MAX; capacity],
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.heap.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.heap.is_empty()
+    }
+
+    pub fn contains(&self, key: usize) -> bool {
+        self.slots[key] != usize::MAX
+    }
+
+    pub fn priority(&self, key: usize) -> Option<P> {
+        self.contains(key).then(|| self.heap[self.slots[key]].0)
+    }
+
+    pub fn peek(&self) -> Option<(P, usize)> {
+        self.heap.first().copied()
+    }
+
+    /// Inserts `key`, or lowers its priority if it is already queued.
+    /// Returns whether anything changed
+    pub fn push_or_decrease(&mut self, key: usize, priority: P) -> bool {
+        match self.priority(key) {
+            Some(old) if old <= priority => false,
+            Some(_) => {
+                let i = self.slots[key];
+                self.heap[i].0 = priority;
+                self.sift_up(i);
+                true
+            }
+            None => {
+                self.heap.push((priority, key));
+                self.slots[key] = self.heap.len() - 1;
+                self.sift_up(self.heap.len() - 1);
+                true
+            }
+        }
+    }
+
+    pub fn pop(&mut self) -> Option<(P, usize)> {
+        if self.heap.is_empty() {
+            return None;
+        }
+
+        let last = self.heap.len() - 1;
+        self.swap(0, last);
+        let (priority, key) = self.heap.pop().unwrap();
+        self.slots[key] = usize::MAX;
+        if !self.heap.is_empty() {
+            self.sift_down(0);
+        }
+
+        Some((priority, key))
+    }
+
+    fn swap(&mut self, a: usize, b: usize) {
+        self.heap.swap(a, b);
+        self.slots[self.heap[a].1] = a;
+        self.slots[self.heap[b].1] = b;
+    }
+
+    fn sift_up(&mut self, mut i: usize) {
+        while i > 0 {
+            let parent = (i - 1) / 2;
+            if self.heap[parent] <= self.heap[i] {
+                break;
+            }
+            self.swap(i, parent);
+            i = parent;
+        }
+    }
+
+    fn sift_down(&mut self, mut i: usize) {
+        let len = self.heap.len();
+        loop {
+            let (left, right) = (2 * i + 1, 2 * i + 2);
+            let mut smallest = i;
+            if left < len && self.heap[left] < self.heap[smallest] {
+                smallest = left;
+            }
+            if right < len && self.heap[right] < self.heap[smallest] {
+                smallest = right;
+            }
+
+            if smallest == i {
+                break;
+            }
+            self.swap(i, smallest);
+            i = smallest;
+        }
+    }
+}
+impl Frontier for IndexedHeap<u64> {
+    fn push(&mut self, cost: u64, node: usize) {
+        self.push_or_decrease(node, cost);
+    }
+
+    fn pop(&mut self) -> Option<(u64, usize)> {
+        IndexedHeap::pop(self)
+    }
+
+    fn is_empty(&self) -> bool {
+        IndexedHeap::is_empty(self)
+    }
+}
+
+/// Dial's bucket queue for monotone searches with edge weights up to
+/// `max_weight`. Pushed priorities must lie within `max_weight` of the last
+/// popped one
+#[derive(Debug, Clone)]
+pub struct BucketQueue<T> {
+    buckets: Vec<Vec<T>>,
+    current: u64,
+    len: usize,
+}
+impl<T> BucketQueue<T> {
+    pub fn new(max_weight: u64) -> Self {
+        Self {
+            buckets: (0..=max_weight).map(|_| vec![]).collect(),
+            current: 0,
+            len: 0,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn push(&mut self, priority: u64, item: T) {
+        let span = self.buckets.len() as u64;
+        assert!(
+            (self.current..self.current + span).contains(&priority),
+            "priority {priority} outside {}..{}",
+            self.current,
+            self.current + span
+        );
+
+        self.buckets[(priority % span) as usize].push(item);
+        self.len += 1;
+    }
+
+    pub fn pop(&mut self) -> Option<(u64, T)> {
+        if self.len == 0 {
+            return None;
+        }
+
+        let span = self.buckets.len() as u64;
+        loop {
+            if let Some(item) = self.buckets[(self.current % span) as usize].pop() {
+                self.len -= 1;
+                return Some((self.current, item));
+            }
+            self.current += 1;
+        }
+    }
+}
+impl Frontier for BucketQueue<usize> {
+    fn push(&mut self, cost: u64, node: usize) {
+        BucketQueue::push(self, cost, node);
+    }
+
+    fn pop(&mut self) -> Option<(u64, usize)> {
+        BucketQueue::pop(self)
+    }
+
+    fn is_empty(&self) -> bool {
+        BucketQueue::is_empty(self)
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum QueueKind {
+    #[default]
+    Binary,
+    Indexed,
+    Bucket,
+}
+impl FromStr for QueueKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "binary" => Ok(Self::Binary),
+            "indexed" => Ok(Self::Indexed),
+            "bucket" => Ok(Self::Bucket),
+            x => Err(format!(
+                "Unknown queue {x:?}, expected binary, indexed or bucket"
+            )),
+        }
+    }
+}
+impl Display for QueueKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Binary => "binary",
+            Self::Indexed => "indexed",
+            Self::Bucket => "bucket",
+        })
+    }
+}
+
+/// Queue picked at runtime, e.g. from a `--queue` flag
+#[derive(Debug, Clone)]
+pub enum Queue {
+    Binary(BinaryQueue),
+    Indexed(IndexedHeap<u64>),
+    Bucket(BucketQueue<usize>),
+}
+impl Queue {
+    /// Queue for nodes `0..len` connected by edges of at most `max_weight`
+    pub fn new(kind: QueueKind, len: usize, max_weight: u64) -> Self {
+        match kind {
+            QueueKind::Binary => Self::Binary(BinaryQueue::new()),
+            QueueKind::Indexed => Self::Indexed(IndexedHeap::new(len)),
+            QueueKind::Bucket => Self::Bucket(BucketQueue::new(max_weight)),
+        }
+    }
+}
+impl Frontier for Queue {
+    fn push(&mut self, cost: u64, node: usize) {
+        match self {
+            Self::Binary(q) => q.push(cost, node),
+            Self::Indexed(q) => Frontier::push(q, cost, node),
+            Self::Bucket(q) => Frontier::push(q, cost, node),
+        }
+    }
+
+    fn pop(&mut self) -> Option<(u64, usize)> {
+        match self {
+            Self::Binary(q) => q.pop(),
+            Self::Indexed(q) => Frontier::pop(q),
+            Self::Bucket(q) => Frontier::pop(q),
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        match self {
+            Self::Binary(q) => q.is_empty(),
+            Self::Indexed(q) => Frontier::is_empty(q),
+            Self::Bucket(q) => Frontier::is_empty(q),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Rng;
+
+    #[test]
+    fn indexed_heap() {
+        let mut heap = IndexedHeap::new(5);
+        assert!(heap.push_or_decrease(0, 50));
+        assert!(heap.push_or_decrease(1, 20));
+        assert!(heap.push_or_decrease(2, 30));
+        assert!(!heap.push_or_decrease(2, 40));
+        assert!(heap.push_or_decrease(0, 10));
+
+        assert_eq!(heap.len(), 3);
+        assert_eq!(heap.priority(2), Some(30));
+        assert_eq!(heap.peek(), Some((10, 0)));
+        assert_eq!(heap.pop(), Some((10, 0)));
+        assert!(!heap.contains(0));
+        assert_eq!(heap.pop(), Some((20, 1)));
+        assert_eq!(heap.pop(), Some((30, 2)));
+        assert_eq!(heap.pop(), None);
+    }
+
+    #[test]
+    fn bucket_queue() {
+        let mut queue = BucketQueue::new(10);
+        queue.push(3, 'a');
+        queue.push(0, 'b');
+        queue.push(10, 'c');
+        assert_eq!(queue.pop(), Some((0, 'b')));
+
+        queue.push(5, 'd');
+        assert_eq!(queue.pop(), Some((3, 'a')));
+        queue.push(13, 'e');
+        assert_eq!(queue.len(), 3);
+        assert_eq!(queue.pop(), Some((5, 'd')));
+        assert_eq!(queue.pop(), Some((10, 'c')));
+        assert_eq!(queue.pop(), Some((13, 'e')));
+        assert!(queue.is_empty());
+    }
+
+    #[test]
+    #[should_panic(expected = "outside")]
+    fn bucket_queue_range() {
+        let mut queue = BucketQueue::new(3);
+        queue.push(4, ());
+    }
+
+    #[test]
+    fn lazy_queues_agree() {
+        let mut rng = Rng::new(7);
+        let mut binary = BinaryQueue::new();
+        let mut bucket = BucketQueue::new(20);
+
+        // Pushes stay within 20 of the last pop, as in Dijkstra
+        let mut floor = 0;
+        for _ in 0..500 {
+            if rng.chance(0.6) {
+                let cost = floor + rng.below(21);
+                binary.push(cost, 0);
+                Frontier::push(&mut bucket, cost, 0);
+            } else {
+                let cost = binary.pop().map(|x| x.0);
+                assert_eq!(Frontier::pop(&mut bucket).map(|x| x.0), cost);
+                floor = cost.unwrap_or(floor);
+            }
+        }
+    }
+}
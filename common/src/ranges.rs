@@ -0,0 +1,313 @@
+use std::{collections::BTreeMap, fmt::Display};
+
+/// Half-open integer interval `start..end`
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Interval {
+    pub start: i64,
+    pub end: i64,
+}
+impl Interval {
+    pub const fn new(start: i64, end: i64) -> Self {
+        Self { start, end }
+    }
+
+    pub const fn with_len(start: i64, len: i64) -> Self {
+        Self::new(start, start + len)
+    }
+
+    pub fn len(&self) -> i64 {
+        (self.end - self.start).max(0)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.end <= self.start
+    }
+
+    pub fn contains(&self, x: i64) -> bool {
+        self.start <= x && x < self.end
+    }
+
+    pub fn overlaps(&self, other: &Self) -> bool {
+        !self.intersection(other).is_empty()
+    }
+
+    pub fn intersection(&self, other: &Self) -> Self {
+        Self::new(self.start.max(other.start), self.end.min(other.end))
+    }
+
+    /// Splits into the parts before and from `at`
+    pub fn split_at(&self, at: i64) -> (Option<Self>, Option<Self>) {
+        let at = at.clamp(self.start, self.end);
+        let non_empty = |x: Self| (!x.is_empty()).then_some(x);
+
+        (
+            non_empty(Self::new(self.start, at)),
+            non_empty(Self::new(at, self.end)),
+        )
+    }
+
+    /// The parts of `self` not covered by `other`
+    pub fn difference(&self, other: &Self) -> (Option<Self>, Option<Self>) {
+        let (before, _) = self.split_at(other.start);
+        let (_, after) = self.split_at(other.end);
+        (before, after)
+    }
+}
+impl Display for Interval {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}..{}", self.start, self.end)
+    }
+}
+impl From<std::ops::Range<i64>> for Interval {
+    fn from(value: std::ops::Range<i64>) -> Self {
+        Self::new(value.start, value.end)
+    }
+}
+
+/// Set of integers stored as sorted, disjoint, non-touching intervals
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct IntervalSet {
+    // start -> end
+    spans: BTreeMap<i64, i64>,
+}
+impl IntervalSet {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds an interval, merging it with any it overlaps or touches
+    pub fn insert(&mut self, interval: impl Into<Interval>) {
+        let Interval { mut start, mut end } = interval.into();
+        if start >= end {
+            return;
+        }
+
+        if let Some((&s, &e)) = self.spans.range(..=start).next_back() {
+            if e >= start {
+                start = s;
+                end = end.max(e);
+                self.spans.remove(&s);
+            }
+        }
+
+        while let Some((&s, &e)) = self.spans.range(start..=end).next() {
+            end = end.max(e);
+            self.spans.remove(&s);
+        }
+
+        self.spans.insert(start, end);
+    }
+
+    pub fn remove(&mut self, interval: impl Into<Interval>) {
+        let removed = interval.into();
+        if removed.is_empty() {
+            return;
+        }
+
+        let overlapping = self
+            .spans
+            .range(..removed.end)
+            .rev()
+            .map(|(s, e)| Interval::new(*s, *e))
+            .take_while(|x| x.end > removed.start)
+            .collect::<Vec<_>>();
+
+        for x in overlapping {
+            self.spans.remove(&x.start);
+
+            let (before, after) = x.difference(&removed);
+            for part in [before, after].into_iter().flatten() {
+                self.spans.insert(part.start, part.end);
+            }
+        }
+    }
+
+    pub fn contains(&self, x: i64) -> bool {
+        self.get(x).is_some()
+    }
+
+    /// The interval containing `x`
+    pub fn get(&self, x: i64) -> Option<Interval> {
+        let (s, e) = self.spans.range(..=x).next_back()?;
+        Some(Interval::new(*s, *e)).filter(|i| i.contains(x))
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = Interval> + '_ {
+        self.spans.iter().map(|(s, e)| Interval::new(*s, *e))
+    }
+
+    /// Leftmost interval at least `len` long
+    pub fn first_fit(&self, len: i64) -> Option<Interval> {
+        self.iter().find(|x| x.len() >= len)
+    }
+
+    /// Total number of covered integers
+    pub fn len(&self) -> i64 {
+        self.iter().map(|x| x.len()).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.spans.is_empty()
+    }
+
+    pub fn union(&self, other: &Self) -> Self {
+        let mut out = self.clone();
+        other.iter().for_each(|x| out.insert(x));
+        out
+    }
+
+    pub fn intersection(&self, other: &Self) -> Self {
+        let mut out = Self::new();
+        let mut a = self.iter().peekable();
+        let mut b = other.iter().peekable();
+
+        while let (Some(x), Some(y)) = (a.peek(), b.peek()) {
+            out.insert(x.intersection(y));
+            if x.end < y.end {
+                a.next();
+            } else {
+                b.next();
+            }
+        }
+
+        out
+    }
+}
+impl<I: Into<Interval>> FromIterator<I> for IntervalSet {
+    fn from_iter<T: IntoIterator<Item = I>>(iter: T) -> Self {
+        let mut out = Self::new();
+        iter.into_iter().for_each(|x| out.insert(x));
+        out
+    }
+}
+
+/// Axis-aligned rectangle, half-open on both axes
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Rect {
+    pub x: Interval,
+    pub y: Interval,
+}
+impl Rect {
+    pub fn new(x: impl Into<Interval>, y: impl Into<Interval>) -> Self {
+        Self {
+            x: x.into(),
+            y: y.into(),
+        }
+    }
+
+    pub fn area(&self) -> i64 {
+        self.x.len() * self.y.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.x.is_empty() || self.y.is_empty()
+    }
+
+    pub fn contains(&self, x: i64, y: i64) -> bool {
+        self.x.contains(x) && self.y.contains(y)
+    }
+
+    pub fn intersection(&self, other: &Self) -> Self {
+        Self::new(self.x.intersection(&other.x), self.y.intersection(&other.y))
+    }
+}
+
+/// Area covered by the union of `rects`, swept column slab by slab
+pub fn union_area(rects: &[Rect]) -> i64 {
+    let mut edges = rects
+        .iter()
+        .filter(|r| !r.is_empty())
+        .flat_map(|r| [r.x.start, r.x.end])
+        .collect::<Vec<_>>();
+    edges.sort();
+    edges.dedup();
+
+    edges
+        .windows(2)
+        .map(|w| {
+            let slab = Interval::new(w[0], w[1]);
+            let covered = rects
+                .iter()
+                .filter(|r| r.x.start <= slab.start && slab.end <= r.x.end)
+                .map(|r| r.y)
+                .collect::<IntervalSet>();
+
+            slab.len() * covered.len()
+        })
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn interval_ops() {
+        let a = Interval::new(0, 10);
+
+        assert_eq!(a.intersection(&(5..15).into()), Interval::new(5, 10));
+        assert!(!a.overlaps(&(10..15).into()));
+        assert_eq!(
+            a.split_at(3),
+            (Some(Interval::new(0, 3)), Some(Interval::new(3, 10)))
+        );
+        assert_eq!(a.split_at(20), (Some(a), None));
+        assert_eq!(
+            a.difference(&(2..4).into()),
+            (Some(Interval::new(0, 2)), Some(Interval::new(4, 10)))
+        );
+    }
+
+    #[test]
+    fn set_insert_remove() {
+        let mut set = IntervalSet::new();
+        set.insert(0..3);
+        set.insert(5..8);
+        set.insert(3..4);
+        assert_eq!(
+            set.iter().collect::<Vec<_>>(),
+            [(0..4).into(), (5..8).into()]
+        );
+
+        set.insert(2..6);
+        assert_eq!(set.iter().collect::<Vec<_>>(), [(0..8).into()]);
+
+        set.remove(2..3);
+        set.remove(6..20);
+        assert_eq!(
+            set.iter().collect::<Vec<_>>(),
+            [(0..2).into(), (3..6).into()]
+        );
+        assert_eq!(set.len(), 5);
+        assert!(set.contains(4));
+        assert!(!set.contains(2));
+        assert_eq!(set.get(5), Some((3..6).into()));
+    }
+
+    #[test]
+    fn set_queries() {
+        let a = [0..2, 4..9, 12..20].into_iter().collect::<IntervalSet>();
+        let b = [1..5, 8..13].into_iter().collect::<IntervalSet>();
+
+        assert_eq!(a.first_fit(3), Some((4..9).into()));
+        assert_eq!(a.first_fit(10), None);
+
+        assert_eq!(
+            a.intersection(&b).iter().collect::<Vec<_>>(),
+            [(1..2).into(), (4..5).into(), (8..9).into(), (12..13).into()]
+        );
+        assert_eq!(a.union(&b).iter().collect::<Vec<_>>(), [(0..20).into()]);
+    }
+
+    #[test]
+    fn rect_union() {
+        let rects = [
+            Rect::new(0..4, 0..4),
+            Rect::new(2..6, 2..6),
+            Rect::new(10..11, 0..1),
+        ];
+
+        assert_eq!(rects[0].intersection(&rects[1]).area(), 4);
+        assert_eq!(union_area(&rects), 16 + 16 - 4 + 1);
+    }
+}
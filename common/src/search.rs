@@ -0,0 +1,183 @@
+use crate::{bits::BitSet, queue::Frontier, Grid, Pos};
+
+/// Result of [`dijkstra`], keeping one shortest-path parent per node
+#[derive(Debug, Clone)]
+pub struct ShortestPaths {
+    dist: Vec<u64>,
+    parent: Vec<usize>,
+}
+impl ShortestPaths {
+    pub fn distance(&self, node: usize) -> Option<u64> {
+        (self.dist[node] != u64::MAX).then_some(self.dist[node])
+    }
+
+    /// Nodes from the start to `node`, both included
+    pub fn path_to(&self, node: usize) -> Option<Vec<usize>> {
+        self.distance(node)?;
+
+        let mut path = vec![node];
+        let mut node = node;
+        while self.parent[node] != usize::MAX {
+            node = self.parent[node];
+            path.push(node);
+        }
+
+        path.reverse();
+        Some(path)
+    }
+}
+
+/// Shortest distances from `start` over nodes `0..len`, stopping once a node
+/// matching `is_goal` is settled. Distances are exact for every node closer
+/// than the goal
+pub fn dijkstra<I>(
+    mut queue: impl Frontier,
+    len: usize,
+    start: usize,
+    mut successors: impl FnMut(usize) -> I,
+    mut is_goal: impl FnMut(usize) -> bool,
+) -> ShortestPaths
+where
+    I: IntoIterator<Item = (usize, u64)>,
+{
+    let mut dist = vec![u64::MAX; len];
+    let mut parent = vec![usize::MAX; len];
+    let mut settled = BitSet::with_capacity(len);
+
+    dist[start] = 0;
+    queue.push(0, start);
+
+    while let Some((cost, node)) = queue.pop() {
+        if !settled.insert(node) {
+            continue;
+        }
+        if is_goal(node) {
+            break;
+        }
+
+        for (next, weight) in successors(node) {
+            let next_cost = cost + weight;
+            if next_cost < dist[next] {
+                dist[next] = next_cost;
+                parent[next] = node;
+                queue.push(next_cost, next);
+            }
+        }
+    }
+
+    ShortestPaths { dist, parent }
+}
+
+/// [`ShortestPaths`] between cells of a grid
+#[derive(Debug, Clone)]
+pub struct GridPaths {
+    width: isize,
+    height: isize,
+    paths: ShortestPaths,
+}
+impl GridPaths {
+    fn index(&self, Pos { x, y }: &Pos) -> Option<usize> {
+        let inside = (0..self.width).contains(x) && (0..self.height).contains(y);
+        inside.then(|| (y * self.width + x) as usize)
+    }
+
+    pub fn distance(&self, p: &Pos) -> Option<u64> {
+        self.paths.distance(self.index(p)?)
+    }
+
+    pub fn path_to(&self, p: &Pos) -> Option<Vec<Pos>> {
+        let path = self.paths.path_to(self.index(p)?)?;
+        let width = self.width as usize;
+        Some(
+            path.into_iter()
+                .map(|i| (i % width, i / width).into())
+                .collect(),
+        )
+    }
+}
+
+/// Dijkstra between cardinal neighbours of `grid`. `cost` is the cost of
+/// entering a cell, or `None` if it can't be entered
+pub fn grid_dijkstra<T>(
+    grid: &Grid<T>,
+    queue: impl Frontier,
+    start: Pos,
+    goal: Option<Pos>,
+    cost: impl Fn(&T) -> Option<u64>,
+) -> GridPaths {
+    let width = grid.width;
+    let index = move |Pos { x, y }: Pos| (y * width + x) as usize;
+    let pos = move |i: usize| Pos::new(i as isize % width, i as isize / width);
+    let goal = goal.map(index);
+    let cost = &cost;
+
+    let paths = dijkstra(
+        queue,
+        (grid.width * grid.height) as usize,
+        index(start),
+        |i| {
+            grid.iter_adjacent_cardinal(pos(i))
+                .filter_map(move |(block, p)| Some((index(p), cost(block)?)))
+        },
+        |i| Some(i) == goal,
+    );
+
+    GridPaths {
+        width: grid.width,
+        height: grid.height,
+        paths,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::queue::{Queue, QueueKind};
+
+    const KINDS: [QueueKind; 3] = [QueueKind::Binary, QueueKind::Indexed, QueueKind::Bucket];
+
+    #[test]
+    fn weighted() {
+        // 0 -> 1 -> 3 is cheaper than 0 -> 3 directly
+        let edges: [&[(usize, u64)]; 5] =
+            [&[(1, 2), (2, 5), (3, 9)], &[(3, 4)], &[(3, 1)], &[], &[]];
+
+        for kind in KINDS {
+            let paths = dijkstra(
+                Queue::new(kind, 5, 9),
+                5,
+                0,
+                |i| edges[i].to_vec(),
+                |_| false,
+            );
+            assert_eq!(paths.distance(3), Some(6), "{kind:?}");
+            assert_eq!(paths.path_to(3), Some(vec![0, 1, 3]));
+            assert_eq!(paths.distance(4), None);
+            assert_eq!(paths.path_to(4), None);
+        }
+    }
+
+    #[test]
+    fn grid() {
+        let grid = Grid::new(
+            ["..#.", ".##.", "...."]
+                .iter()
+                .map(|row| row.chars().collect())
+                .collect(),
+        );
+        let open = |c: &char| (*c == '.').then_some(1);
+
+        for kind in KINDS {
+            let queue = Queue::new(kind, 12, 1);
+            let paths = grid_dijkstra(&grid, queue, Pos::new(0, 0), None, open);
+            assert_eq!(paths.distance(&Pos::new(3, 0)), Some(7), "{kind:?}");
+            assert_eq!(paths.distance(&Pos::new(2, 0)), None);
+            assert_eq!(paths.path_to(&Pos::new(3, 0)).unwrap().len(), 8);
+        }
+
+        let queue = Queue::new(QueueKind::Bucket, 12, 1);
+        let paths = grid_dijkstra(&grid, queue, Pos::new(0, 0), Some(Pos::new(0, 2)), open);
+        assert_eq!(paths.distance(&Pos::new(0, 2)), Some(2));
+        assert_eq!(paths.distance(&Pos::new(3, 0)), None);
+    }
+}
@@ -1,80 +1,304 @@
-use std::io::Read as _;
+use common::{parse, Args, Rng};
+use std::{collections::HashMap, io::Read as _};
 
-fn sorted_difference(a: &[i32], b: &[i32]) -> i32 {
-    fn sorted(x: &[i32]) -> Vec<i32> {
-        let mut x = x.to_vec();
-        x.sort();
-        x
+fn sorted(x: &[i64]) -> Vec<i64> {
+    let mut x = x.to_vec();
+    x.sort();
+    x
+}
+
+fn counts(x: &[i64]) -> HashMap<i64, i64> {
+    let mut occurences = HashMap::new();
+
+    for x in x {
+        *occurences.entry(*x).or_insert(0) += 1;
     }
 
-    let a = sorted(a);
-    let b = sorted(b);
+    occurences
+}
+
+/// Column pair with the largest contribution to a row
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Contribution {
+    pair: (usize, usize),
+    value: i128,
+}
 
-    a.iter().zip(b).map(|(a, b)| a.abs_diff(b) as i32).sum()
+/// Lists side by side, with each column's sorted values and counts cached.
+/// Sums are `i128`, so no value or count in an `i64` list can overflow them
+#[derive(Debug, Clone)]
+struct Columns {
+    columns: Vec<Vec<i64>>,
+    sorted: Vec<Vec<i64>>,
+    counts: Vec<HashMap<i64, i64>>,
 }
+impl Columns {
+    fn new(columns: Vec<Vec<i64>>) -> Self {
+        Self {
+            sorted: columns.iter().map(|x| sorted(x)).collect(),
+            counts: columns.iter().map(|x| counts(x)).collect(),
+            columns,
+        }
+    }
+
+    fn parse(input: &str) -> Self {
+        let mut columns: Vec<Vec<i64>> = vec![];
+
+        for (i, line) in input.lines().enumerate() {
+            let row = parse::numbers_in::<i64>(line).unwrap_or_else(|e| {
+                let token = line[e.offset..]
+                    .split_whitespace()
+                    .next()
+                    .unwrap_or_default();
+                panic!("line {}, column {}: {token} isn't an i64", i + 1, e.column)
+            });
+            if row.is_empty() {
+                continue;
+            }
+
+            if columns.is_empty() {
+                columns = vec![vec![]; row.len()];
+            }
+            assert_eq!(
+                row.len(),
+                columns.len(),
+                "line {} has {} columns, expected {}",
+                i + 1,
+                row.len(),
+                columns.len()
+            );
+
+            for (column, x) in columns.iter_mut().zip(row) {
+                column.push(x);
+            }
+        }
+
+        Self::new(columns)
+    }
+
+    fn len(&self) -> usize {
+        self.columns.len()
+    }
 
-fn similarity(a: &[i32], b: &[i32]) -> i32 {
-    use std::collections::hash_map::HashMap;
+    fn rows(&self) -> usize {
+        self.columns.first().map_or(0, |x| x.len())
+    }
+
+    /// Distance between the `row`th smallest values of two columns
+    fn row_distance(&self, (a, b): (usize, usize), row: usize) -> i128 {
+        self.sorted[a][row].abs_diff(self.sorted[b][row]) as i128
+    }
+
+    /// Row `row` of column `a`, weighted by how often it appears in `b`
+    fn row_similarity(&self, (a, b): (usize, usize), row: usize) -> i128 {
+        let x = self.columns[a][row];
+        x as i128 * self.counts[b].get(&x).copied().unwrap_or(0) as i128
+    }
 
-    let mut occurences = HashMap::<_, _>::new();
+    fn distance(&self, pair: (usize, usize)) -> i128 {
+        (0..self.rows()).map(|i| self.row_distance(pair, i)).sum()
+    }
 
-    for x in b {
-        occurences.entry(*x).and_modify(|e| *e += 1).or_insert(1);
+    fn similarity(&self, pair: (usize, usize)) -> i128 {
+        (0..self.rows()).map(|i| self.row_similarity(pair, i)).sum()
     }
 
-    let mut sum = 0;
+    /// Entry `[a][b]` is `f((a, b))`. Distances are symmetric, similarities
+    /// are not
+    fn matrix(&self, f: impl Fn(&Self, (usize, usize)) -> i128) -> Vec<Vec<i128>> {
+        (0..self.len())
+            .map(|a| (0..self.len()).map(|b| f(self, (a, b))).collect())
+            .collect()
+    }
 
-    for x in a {
-        let mult = occurences.get(x).cloned().unwrap_or(0);
-        sum += x * mult;
+    fn distance_matrix(&self) -> Vec<Vec<i128>> {
+        self.matrix(Self::distance)
     }
 
-    sum
+    fn similarity_matrix(&self) -> Vec<Vec<i128>> {
+        self.matrix(Self::similarity)
+    }
+
+    fn pairs(&self) -> impl Iterator<Item = (usize, usize)> + '_ {
+        (0..self.len()).flat_map(move |a| {
+            (0..self.len())
+                .filter(move |b| a != *b)
+                .map(move |b| (a, b))
+        })
+    }
+
+    /// Pair contributing most to index `i` of `f`. Earlier pairs win ties
+    fn top_pair(
+        &self,
+        f: fn(&Self, (usize, usize), usize) -> i128,
+        i: usize,
+    ) -> Option<Contribution> {
+        self.pairs()
+            .map(|pair| Contribution {
+                pair,
+                value: f(self, pair, i),
+            })
+            .reduce(|best, x| if x.value > best.value { x } else { best })
+    }
+
+    /// Pair contributing most to the distance between the `rank`th smallest
+    /// values, which are usually on different input rows
+    fn distance_breakdown(&self, rank: usize) -> Option<Contribution> {
+        self.top_pair(Self::row_distance, rank)
+    }
+
+    /// Pair contributing most to the similarity of input row `row`
+    fn similarity_breakdown(&self, row: usize) -> Option<Contribution> {
+        self.top_pair(Self::row_similarity, row)
+    }
+}
+
+fn format_matrix(matrix: &[Vec<i128>]) -> String {
+    let width = matrix
+        .iter()
+        .flatten()
+        .map(|x| x.to_string().len())
+        .max()
+        .unwrap_or(0);
+
+    let header = (0..matrix.len())
+        .map(|b| format!(" {b:>width$}"))
+        .collect::<String>();
+    let rows = matrix.iter().enumerate().map(|(a, row)| {
+        let cells = row
+            .iter()
+            .map(|x| format!(" {x:>width$}"))
+            .collect::<String>();
+        format!("{a:>3}{cells}\n")
+    });
+
+    format!("   {header}\n") + &rows.collect::<String>()
+}
+
+fn generate(rng: &mut Rng, args: &Args) -> String {
+    let lines = args.get_or("lines", 1000);
+    let columns = args.get_or("columns", 2);
+
+    (0..lines)
+        .map(|_| {
+            let row = (0..columns)
+                .map(|_| rng.range(10000..100000).to_string())
+                .collect::<Vec<_>>();
+            row.join("   ") + "\n"
+        })
+        .collect()
 }
 
 fn main() {
+    let args = Args::from_env();
+    if args.run_generate(generate) {
+        return;
+    }
+
     let input = {
         let mut buf = String::new();
         std::io::stdin().read_to_string(&mut buf).unwrap();
         buf
     };
 
-    let (a, b): (Vec<_>, Vec<_>) = input
-        .lines()
-        .map(|line| {
-            let mut parts = line.split_whitespace();
+    let columns = Columns::parse(&input);
+    assert!(columns.len() >= 2, "need at least two columns");
 
-            let a = parts.next().unwrap().parse::<i32>().unwrap();
-            let b = parts.next().unwrap().parse::<i32>().unwrap();
+    println!("Part 1: {}", columns.distance((0, 1)));
+    println!("Part 2: {}", columns.similarity((0, 1)));
 
-            (a, b)
-        })
-        .unzip();
-
-    let diff = sorted_difference(&a, &b);
-    println!("Part 1: {}", diff);
+    if columns.len() > 2 || args.flag("matrix") {
+        print!("Distances:\n{}", format_matrix(&columns.distance_matrix()));
+        print!(
+            "Similarities:\n{}",
+            format_matrix(&columns.similarity_matrix())
+        );
+    }
 
-    let similarity = similarity(&a, &b);
-    println!("Part 2: {similarity}");
+    if args.flag("breakdown") {
+        // Distances pair values by rank after sorting, similarities by input row
+        for rank in 0..columns.rows() {
+            let distance = columns.distance_breakdown(rank).unwrap();
+            println!(
+                "Rank {}: distance {} from {:?}",
+                rank + 1,
+                distance.value,
+                distance.pair
+            );
+        }
+        for row in 0..columns.rows() {
+            let similarity = columns.similarity_breakdown(row).unwrap();
+            println!(
+                "Row {}: similarity {} from {:?}",
+                row + 1,
+                similarity.value,
+                similarity.pair
+            );
+        }
+    }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::{similarity, sorted_difference};
+    use crate::{format_matrix, Columns, Contribution};
+
+    fn example() -> Columns {
+        Columns::new(vec![vec![3, 4, 2, 1, 3, 3], vec![4, 3, 5, 3, 9, 3]])
+    }
 
     #[test]
     fn part1() {
-        let a = [3, 4, 2, 1, 3, 3];
-        let b = [4, 3, 5, 3, 9, 3];
-
-        assert_eq!(sorted_difference(&a, &b), 11)
+        assert_eq!(example().distance((0, 1)), 11)
     }
 
     #[test]
     fn part2() {
-        let a = [3, 4, 2, 1, 3, 3];
-        let b = [4, 3, 5, 3, 9, 3];
+        assert_eq!(example().similarity((0, 1)), 31)
+    }
+
+    #[test]
+    fn matrices() {
+        let columns = Columns::parse("3 4 3\n4 3 3\n2 5 4\n\n1 3 2\n");
+        assert_eq!(columns.len(), 3);
+        assert_eq!(columns.rows(), 4);
 
-        assert_eq!(similarity(&a, &b), 31)
+        let distance = columns.distance_matrix();
+        assert_eq!(distance, [[0, 5, 2], [5, 0, 3], [2, 3, 0]]);
+        assert_eq!(
+            columns.similarity_matrix(),
+            [[10, 10, 12], [10, 21, 16], [12, 16, 18]]
+        );
+        assert_eq!(
+            format_matrix(&distance),
+            "    0 1 2\n  0 0 5 2\n  1 5 0 3\n  2 2 3 0\n"
+        );
+
+        assert_eq!(
+            columns.distance_breakdown(2).unwrap(),
+            Contribution {
+                pair: (0, 1),
+                value: 1
+            }
+        );
+        assert_eq!(
+            columns.similarity_breakdown(2).unwrap(),
+            Contribution {
+                pair: (2, 0),
+                value: 4
+            }
+        );
+    }
+
+    #[test]
+    fn large_values() {
+        let columns = Columns::parse(&format!("{0} {0}\n{0} {0}\n-{0} 0\n", i64::MAX));
+        assert_eq!(columns.similarity((0, 1)), 4 * i64::MAX as i128);
+        assert_eq!(columns.distance((0, 1)), i64::MAX as i128);
+    }
+
+    #[test]
+    #[should_panic(expected = "line 2, column 3: 9223372036854775808 isn't an i64")]
+    fn too_large() {
+        Columns::parse(&format!("1 2\n3 {}\n", i64::MAX as u64 + 1));
     }
 }
@@ -1,6 +1,6 @@
 use std::ops::{Add, Sub};
 
-use common::timed;
+use common::{timed, Args, Rng};
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 struct Pos {
@@ -173,7 +173,32 @@ fn find_trails_distinct(grid: &Grid) -> u32 {
     scores.sum::<u32>()
 }
 
+fn generate(rng: &mut Rng, args: &Args) -> String {
+    let width = args.get_or("width", 53);
+    let height = args.get_or("height", width);
+
+    // Heights mostly follow a diagonal gradient so that trails are common
+    (0..height)
+        .map(|y| {
+            let row = (0..width).map(|x| {
+                let height = if rng.chance(0.8) {
+                    (x + y) % 10
+                } else {
+                    rng.index(10)
+                };
+                char::from(b'0' + height as u8)
+            });
+            row.chain(['\n']).collect::<String>()
+        })
+        .collect()
+}
+
 fn main() {
+    let args = Args::from_env();
+    if args.run_generate(generate) {
+        return;
+    }
+
     let grid = Grid::new(&common::read_stdin());
 
     let (time, score) = timed(|| find_trails(&grid));
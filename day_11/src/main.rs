@@ -1,18 +1,8 @@
-use common::timed;
+use common::{math, params::Schema, timed, Args, Rng};
 use std::collections::HashMap;
 
-fn count_digits(x: u64) -> u64 {
-    ((x as f64).log10().floor() + 1.0) as u64
-}
-
 fn split_number(x: u64) -> (u64, u64) {
-    let digits = count_digits(x);
-    let power = 10_u64.pow(digits as u32 / 2);
-
-    let high = x / power;
-    let low = x % power;
-
-    (high, low)
+    math::split_digits(x, math::digits(x) / 2)
 }
 
 type Memo = HashMap<(u8, u64), u64>;
@@ -24,7 +14,7 @@ fn blink(n: u8, stone: u64, memo: &mut Memo) -> u64 {
         *stored
     } else if stone == 0 {
         blink(n - 1, 1, memo)
-    } else if count_digits(stone) % 2 == 0 {
+    } else if math::digits(stone).is_multiple_of(2) {
         let (high, low) = split_number(stone);
         blink(n - 1, high, memo) + blink(n - 1, low, memo)
     } else {
@@ -39,7 +29,28 @@ fn blink_multiple(items: &[u64], n: u8, memo: &mut Memo) -> u64 {
     items.iter().fold(0, |acc, x| acc + blink(n, *x, memo))
 }
 
+fn schema() -> Schema {
+    Schema::new()
+        .param("blinks-1", 25u8, "blinks for part 1")
+        .param("blinks-2", 75u8, "blinks for part 2")
+}
+
+fn generate(rng: &mut Rng, args: &Args) -> String {
+    let stones = args.get_or("stones", 8);
+
+    let stones = (0..stones)
+        .map(|_| rng.range(0..10_000_000).to_string())
+        .collect::<Vec<_>>();
+    stones.join(" ") + "\n"
+}
+
 fn main() {
+    let args = Args::from_env();
+    if args.run_generate(generate) {
+        return;
+    }
+
+    let params = args.params(&schema());
     let input: Vec<u64> = common::read_stdin()
         .split_ascii_whitespace()
         .map(|x| x.parse().unwrap())
@@ -47,10 +58,10 @@ fn main() {
 
     let mut memo = Memo::default();
 
-    let (time, stones) = timed(|| blink_multiple(&input, 25, &mut memo));
+    let (time, stones) = timed(|| blink_multiple(&input, params.get("blinks-1"), &mut memo));
     println!("Part 1: {} in {}ms", stones, time.as_millis());
 
-    let (time, stones) = timed(|| blink_multiple(&input, 75, &mut memo));
+    let (time, stones) = timed(|| blink_multiple(&input, params.get("blinks-2"), &mut memo));
     println!("Part 2: {} in {}ms", stones, time.as_millis());
 }
 
@@ -66,6 +77,7 @@ mod tests {
         assert_eq!(split_number(1234), (12, 34));
         assert_eq!(split_number(123456), (123, 456));
         assert_eq!(split_number(12345678), (1234, 5678));
+        assert_eq!(split_number(9999999999999999), (99999999, 99999999));
     }
 
     #[test]
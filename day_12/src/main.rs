@@ -1,5 +1,4 @@
-use common::{timed, vectors, Pos};
-use std::collections::HashSet;
+use common::{bits::BitGrid, timed, vectors, Args, Pos, Rng};
 
 #[derive(Debug)]
 struct Grid {
@@ -39,28 +38,30 @@ impl Grid {
     }
 }
 
-fn get_area(c: char, pos: &Pos, grid: &Grid, visited: &mut HashSet<Pos>) -> HashSet<Pos> {
-    let mut area = HashSet::<Pos>::default();
+fn get_area(c: char, pos: &Pos, grid: &Grid, visited: &mut BitGrid) -> Vec<Pos> {
+    let mut area = vec![*pos];
+    let mut stack = vec![*pos];
 
     visited.insert(*pos);
-    area.insert(*pos);
 
-    for vec in vectors::CARDINAL {
-        let next = *pos + vec;
-        if visited.contains(&next) || is_different(c, &next, grid) {
-            continue;
-        } else {
+    while let Some(pos) = stack.pop() {
+        for vec in vectors::CARDINAL {
+            let next = pos + vec;
+            if visited.contains(&next) || is_different(c, &next, grid) {
+                continue;
+            }
+
             visited.insert(next);
-            area.insert(next);
-            area.extend(get_area(c, &next, grid, visited));
+            area.push(next);
+            stack.push(next);
         }
     }
 
     area
 }
 
-fn get_all_areas(grid: &Grid) -> Vec<(char, HashSet<Pos>)> {
-    let mut visited = HashSet::new();
+fn get_all_areas(grid: &Grid) -> Vec<(char, Vec<Pos>)> {
+    let mut visited = BitGrid::new(grid.width, grid.height);
     let mut areas = vec![];
 
     for (c, pos) in grid.iter() {
@@ -76,7 +77,7 @@ fn is_different(c: char, pos: &Pos, grid: &Grid) -> bool {
     !grid.is_inside(pos) || *grid.get(pos) != c
 }
 
-fn get_perimeter(c: char, patch: &HashSet<Pos>, grid: &Grid) -> u32 {
+fn get_perimeter(c: char, patch: &[Pos], grid: &Grid) -> u32 {
     let mut perimeter = 0;
 
     for pos in patch {
@@ -100,14 +101,14 @@ fn get_tangents(vec: Pos) -> (Pos, Pos) {
     }
 }
 
-fn get_sides(c: char, patch: &HashSet<Pos>, grid: &Grid) -> u32 {
+fn get_sides(c: char, patch: &[Pos], grid: &Grid) -> u32 {
     fn visit_direction(
         c: char,
         pos: &Pos,
         vec: &Pos,
         tangent: &Pos,
         grid: &Grid,
-        visited: &mut HashSet<Pos>,
+        visited: &mut BitGrid,
     ) {
         let mut cursor = *pos + *tangent;
         while grid.is_inside(&cursor) && *grid.get(&cursor) == c {
@@ -121,14 +122,13 @@ fn get_sides(c: char, patch: &HashSet<Pos>, grid: &Grid) -> u32 {
         }
     }
 
-    fn get_sides_of_direction(c: char, patch: &HashSet<Pos>, grid: &Grid, vec: &Pos) -> u32 {
-        let mut visited = HashSet::<Pos>::new();
+    fn get_sides_of_direction(c: char, patch: &[Pos], grid: &Grid, vec: &Pos) -> u32 {
+        let mut visited = BitGrid::new(grid.width, grid.height);
         let mut sides = 0;
         for pos in patch {
-            if visited.contains(pos) {
+            if !visited.insert(*pos) {
                 continue;
             }
-            visited.insert(*pos);
 
             let next = *pos + *vec;
             if is_different(c, &next, grid) {
@@ -148,7 +148,7 @@ fn get_sides(c: char, patch: &HashSet<Pos>, grid: &Grid) -> u32 {
         .sum()
 }
 
-fn get_total_price(patches: &[(char, HashSet<Pos>)], grid: &Grid) -> u64 {
+fn get_total_price(patches: &[(char, Vec<Pos>)], grid: &Grid) -> u64 {
     patches
         .iter()
         .map(|(c, patch)| {
@@ -160,7 +160,7 @@ fn get_total_price(patches: &[(char, HashSet<Pos>)], grid: &Grid) -> u64 {
         .sum()
 }
 
-fn get_total_price_with_discount(patches: &[(char, HashSet<Pos>)], grid: &Grid) -> u64 {
+fn get_total_price_with_discount(patches: &[(char, Vec<Pos>)], grid: &Grid) -> u64 {
     patches
         .iter()
         .map(|(c, patch)| {
@@ -172,7 +172,36 @@ fn get_total_price_with_discount(patches: &[(char, HashSet<Pos>)], grid: &Grid)
         .sum()
 }
 
+fn generate(rng: &mut Rng, args: &Args) -> String {
+    let width = args.get_or("width", 140);
+    let height = args.get_or("height", width);
+    let plants = ('A'..='Z')
+        .take(args.get_or("plants", 26))
+        .collect::<Vec<_>>();
+
+    // Copy a neighbouring plant most of the time so that regions form
+    let mut grid = vec![vec!['A'; width]; height];
+    for y in 0..height {
+        for x in 0..width {
+            grid[y][x] = match rng.below(10) {
+                0..=3 if x > 0 => grid[y][x - 1],
+                4..=7 if y > 0 => grid[y - 1][x],
+                _ => *rng.choose(&plants),
+            };
+        }
+    }
+
+    grid.into_iter()
+        .map(|row| row.into_iter().chain(['\n']).collect::<String>())
+        .collect()
+}
+
 fn main() {
+    let args = Args::from_env();
+    if args.run_generate(generate) {
+        return;
+    }
+
     let grid = Grid::new(&common::read_stdin());
 
     let areas = get_all_areas(&grid);
@@ -194,7 +223,9 @@ mod tests {
     fn get_area_test() {
         let grid = Grid::new(include_str!("../area_example.txt"));
 
-        let area = get_area('A', &Pos { x: 0, y: 0 }, &grid, &mut HashSet::default());
+        let mut visited = BitGrid::new(grid.width, grid.height);
+        let mut area = get_area('A', &Pos { x: 0, y: 0 }, &grid, &mut visited);
+        area.sort_by_key(|p| (p.y, p.x));
 
         let a_areas = [
             Pos { x: 0, y: 0 },
@@ -205,12 +236,12 @@ mod tests {
             Pos { x: 1, y: 1 },
             Pos { x: 2, y: 1 },
             Pos { x: 3, y: 1 },
-        ]
-        .into();
+        ];
 
         assert_eq!(area, a_areas);
 
-        let all = get_all_areas(&grid);
+        let mut all = get_all_areas(&grid);
+        all[0].1.sort_by_key(|p| (p.y, p.x));
         assert_eq!(all[0].1, a_areas);
     }
 
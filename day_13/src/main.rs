@@ -1,22 +1,18 @@
-use common::timed;
+use common::{
+    linalg::{self, Solution},
+    math,
+    parse::{ParseResult, Parser},
+    timed, Args, Rng,
+};
 
 #[derive(Debug, Clone, Copy, PartialEq)]
 struct Pos {
-    x: f64,
-    y: f64,
+    x: i64,
+    y: i64,
 }
-impl std::ops::Mul<f64> for Pos {
+impl std::ops::Add<i64> for Pos {
     type Output = Self;
-    fn mul(self, rhs: f64) -> Self::Output {
-        Self {
-            x: self.x * rhs,
-            y: self.y * rhs,
-        }
-    }
-}
-impl std::ops::Add<f64> for Pos {
-    type Output = Self;
-    fn add(self, rhs: f64) -> Self::Output {
+    fn add(self, rhs: i64) -> Self::Output {
         Self {
             x: self.x + rhs,
             y: self.y + rhs,
@@ -31,96 +27,156 @@ struct Machine {
     target: Pos,
 }
 
+/// Cheapest non-negative `(a, b)` with `a * p + b * q == t`, used when both
+/// buttons move along the same line
+fn cheapest_on_line(p: i64, q: i64, t: i64) -> Option<(i64, i64)> {
+    let exact = |x: i64, d: i64| (x % d == 0 && x / d >= 0).then(|| x / d);
+
+    match (p, q) {
+        (0, 0) => (t == 0).then_some((0, 0)),
+        (_, 0) => exact(t, p).map(|a| (a, 0)),
+        (0, _) => exact(t, q).map(|b| (0, b)),
+        _ => {
+            let (g, x, y) = math::extended_gcd(p, q);
+            if t % g != 0 {
+                return None;
+            }
+
+            // Every solution is (a0 + k * da, b0 - k * db)
+            let (a0, b0) = (x as i128 * (t / g) as i128, y as i128 * (t / g) as i128);
+            let (da, db) = ((q / g) as i128, (p / g) as i128);
+
+            // Range of k keeping both counts non-negative
+            let mut low = i128::MIN;
+            let mut high = i128::MAX;
+            for (c, d) in [(a0, da), (b0, -db)] {
+                // c + k * d >= 0
+                if d > 0 {
+                    low = low.max(-c.div_euclid(d));
+                } else if d < 0 {
+                    high = high.min(c.div_euclid(-d));
+                }
+            }
+
+            // Cost is linear in k, so the cheapest end of the range wins
+            let slope = 3 * da - db;
+            let k = if slope >= 0 { low } else { high };
+            if low > high || k == i128::MIN || k == i128::MAX {
+                return None;
+            }
+
+            let a = i64::try_from(a0 + k * da).ok()?;
+            let b = i64::try_from(b0 - k * db).ok()?;
+            Some((a, b))
+        }
+    }
+}
+
 fn get_presses(
     Machine {
         a_incr,
         b_incr,
         target,
     }: &Machine,
-) -> (f64, f64) {
+) -> Option<(i64, i64)> {
     // A: X+94, Y+34
     // B: X+22, Y+67
     // 94x + 22y = 8400
     // 34x + 67y = 5400
 
-    let a = a_incr.x;
-    let b = b_incr.x;
-    let c = target.x;
-
-    let d = a_incr.y;
-    let e = b_incr.y;
-    let f = target.y;
+    let a = [[a_incr.x, b_incr.x], [a_incr.y, b_incr.y]];
+    let b = [target.x, target.y];
 
-    let x = (c * e - b * f) / (a * e - b * d);
-    let y = (c - a * x) / b;
-    (x, y)
-}
+    // Solution is only valid if num of presses is a non-negative integer
+    if let Some([x, y]) = linalg::solve_2x2(a, b) {
+        let a = i64::try_from(x.to_integer()?).ok()?;
+        let b = i64::try_from(y.to_integer()?).ok()?;
+        return (a >= 0 && b >= 0).then_some((a, b));
+    }
 
-fn validate((a_presses, b_presses): (f64, f64)) -> bool {
-    // Solution is correct if num of presses is an integer
-    a_presses == a_presses.floor() && b_presses == b_presses.floor()
+    match linalg::solve(&a, &b) {
+        Solution::Unique(_) => unreachable!("solve_2x2 covers invertible matrices"),
+        Solution::Infinite { .. } => {
+            // Consistent and collinear, so either row describes the line
+            if (a_incr.x, b_incr.x) != (0, 0) {
+                cheapest_on_line(a_incr.x, b_incr.x, target.x)
+            } else {
+                cheapest_on_line(a_incr.y, b_incr.y, target.y)
+            }
+        }
+        Solution::Inconsistent => None,
+    }
 }
 
-fn get_token_cost((a, b): (f64, f64)) -> f64 {
-    (a * 3.0) + b
+fn get_token_cost((a, b): (i64, i64)) -> i64 {
+    (a * 3) + b
 }
 
 fn parse_input(input: &str) -> Vec<Machine> {
-    fn read_line(line: &str) -> Pos {
-        fn read_part(part: &str) -> f64 {
-            part.chars()
-                .filter(|x| x.is_ascii_digit())
-                .collect::<String>()
-                .parse::<f64>()
-                .unwrap()
-        }
-
-        let (x, y) = line.split_once(',').unwrap();
-
-        let x = read_part(x);
-        let y = read_part(y);
-        Pos { x, y }
+    fn read_line(p: &mut Parser, x_tag: &str, y_tag: &str) -> ParseResult<Pos> {
+        p.tag(x_tag)?;
+        let x = p.int()?;
+        p.tag(y_tag)?;
+        let y = p.int()?;
+        Ok(Pos { x, y })
     }
 
-    let mut machines = vec![];
+    Parser::new(input)
+        .many(|p| {
+            let a_incr = read_line(p, "Button A: X", ", Y")?;
+            p.newline()?;
+            let b_incr = read_line(p, "Button B: X", ", Y")?;
+            p.newline()?;
+            let target = read_line(p, "Prize: X=", ", Y=")?;
 
-    let mut lines = input.lines();
-    while let Some(a_line) = lines.next() {
-        let b_line = lines.next().unwrap();
-        let target_line = lines.next().unwrap();
+            Ok(Machine {
+                a_incr,
+                b_incr,
+                target,
+            })
+        })
+        .unwrap_or_else(|e| panic!("Invalid input: {e}"))
+}
 
-        let a_incr = read_line(a_line);
-        let b_incr = read_line(b_line);
-        let target = read_line(target_line);
+fn generate(rng: &mut Rng, args: &Args) -> String {
+    let machines = args.get_or("machines", 320);
 
-        machines.push(Machine {
-            a_incr,
-            b_incr,
-            target,
-        });
+    let machines = (0..machines).map(|_| {
+        let [ax, ay, bx, by] = [(); 4].map(|_| rng.range(10..100));
+        let (a, b) = (rng.range(0..101), rng.range(0..101));
 
-        if lines.next().is_none() {
-            break;
+        let mut prize = (a * ax + b * bx, a * ay + b * by);
+        if rng.chance(0.5) {
+            prize.0 += rng.range(1..10);
         }
-    }
 
-    machines
+        format!(
+            "Button A: X+{ax}, Y+{ay}\nButton B: X+{bx}, Y+{by}\nPrize: X={}, Y={}\n",
+            prize.0, prize.1
+        )
+    });
+
+    machines.collect::<Vec<_>>().join("\n")
 }
 
 fn main() {
+    let args = Args::from_env();
+    if args.run_generate(generate) {
+        return;
+    }
+
     let machines = parse_input(&common::read_stdin());
 
     let (time, tokens) = timed(|| {
         machines
             .iter()
-            .map(get_presses)
-            .filter(|x| validate(*x))
+            .filter_map(get_presses)
             .map(get_token_cost)
-            .sum::<f64>()
+            .sum::<i64>()
     });
     println!("Part 1: {tokens} in {}μs", time.as_micros());
 
-    const TARGET_MODIFIER: f64 = 10_000_000_000_000.0;
+    const TARGET_MODIFIER: i64 = 10_000_000_000_000;
 
     let (time, tokens) = timed(|| {
         machines
@@ -129,10 +185,9 @@ fn main() {
                 target: m.target + TARGET_MODIFIER,
                 ..*m
             })
-            .map(|m| get_presses(&m))
-            .filter(|x| validate(*x))
+            .filter_map(|m| get_presses(&m))
             .map(get_token_cost)
-            .sum::<f64>()
+            .sum::<i64>()
     });
     println!("Part 2: {tokens} in {}μs", time.as_micros());
 }
@@ -146,34 +201,59 @@ mod tests {
 
     #[test]
     fn part1() {
-        let a_incr = Pos { x: 94.0, y: 34.0 };
-        let b_incr = Pos { x: 22.0, y: 67.0 };
-        let target = Pos {
-            x: 8400.0,
-            y: 5400.0,
-        };
+        let a_incr = Pos { x: 94, y: 34 };
+        let b_incr = Pos { x: 22, y: 67 };
+        let target = Pos { x: 8400, y: 5400 };
 
         let presses = get_presses(&Machine {
             a_incr,
             b_incr,
             target,
         });
-        assert_eq!(presses, (80.0, 40.0));
-        assert!(validate(presses));
-        assert_eq!(get_token_cost(presses), 280.0);
-
-        let a_incr = Pos { x: 26.0, y: 66.0 };
-        let b_incr = Pos { x: 67.0, y: 21.0 };
-        let target = Pos {
-            x: 12748.0,
-            y: 12176.0,
-        };
+        assert_eq!(presses, Some((80, 40)));
+        assert_eq!(presses.map(get_token_cost), Some(280));
+
+        let a_incr = Pos { x: 26, y: 66 };
+        let b_incr = Pos { x: 67, y: 21 };
+        let target = Pos { x: 12748, y: 12176 };
 
         let presses = get_presses(&Machine {
             a_incr,
             b_incr,
             target,
         });
-        assert!(!validate(presses))
+        assert_eq!(presses, None);
+
+        let presses = get_presses(&Machine {
+            a_incr,
+            b_incr,
+            target: target + 10_000_000_000_000,
+        });
+        assert_eq!(presses, Some((118679050709, 103199174542)));
+    }
+
+    #[test]
+    fn collinear() {
+        // B moves three times as far as A for a third of the cost
+        let machine = |target| Machine {
+            a_incr: Pos { x: 2, y: 4 },
+            b_incr: Pos { x: 6, y: 12 },
+            target,
+        };
+
+        assert_eq!(get_presses(&machine(Pos { x: 20, y: 40 })), Some((1, 3)));
+        assert_eq!(get_presses(&machine(Pos { x: 20, y: 41 })), None);
+        assert_eq!(get_presses(&machine(Pos { x: 3, y: 6 })), None);
+
+        // A is the better deal per distance here
+        assert_eq!(cheapest_on_line(10, 3, 23), Some((2, 1)));
+        assert_eq!(cheapest_on_line(0, 0, 0), Some((0, 0)));
+        assert_eq!(cheapest_on_line(5, 0, 10), Some((2, 0)));
+
+        // Bezout coefficients times the offset overflow i64
+        assert_eq!(
+            cheapest_on_line(999_999_937, 999_999_929, 1_000_000_000_000_000_000),
+            Some((875_000_568, 124_999_496))
+        );
     }
 }
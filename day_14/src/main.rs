@@ -1,4 +1,9 @@
-use common::timed;
+use common::{
+    math,
+    params::{Params, Schema},
+    parse::{self, ParseResult, Parser},
+    timed, Args, Rng,
+};
 use std::collections::HashSet;
 
 use common::Pos;
@@ -17,6 +22,14 @@ struct Grid {
     robots: Vec<Robot>,
 }
 impl Grid {
+    fn new(robots: Vec<Robot>, params: &Params) -> Self {
+        Self {
+            width: params.get("width"),
+            height: params.get("height"),
+            robots,
+        }
+    }
+
     fn wrap_position(&self, pos: &Pos) -> Pos {
         Pos {
             x: pos.x.rem_euclid(self.width),
@@ -75,6 +88,39 @@ impl Grid {
         top_left * top_right * bottom_left * bottom_right
     }
 
+    /// Steps after which every robot is back where it started
+    fn period(&self) -> isize {
+        math::lcm(self.width as i64, self.height as i64) as isize
+    }
+
+    /// Columns repeat every `width` steps and rows every `height`, so find the
+    /// step where each axis is most clustered and combine them with the CRT
+    fn find_tree(&self) -> Option<isize> {
+        fn spread(values: impl Iterator<Item = isize>) -> i64 {
+            let (mut n, mut sum, mut squares) = (0, 0, 0);
+            for x in values {
+                n += 1;
+                sum += x as i64;
+                squares += (x * x) as i64;
+            }
+            n * squares - sum * sum
+        }
+
+        let x_step = (0..self.width)
+            .min_by_key(|t| spread(self.simulate(*t).robots.iter().map(|r| r.position.x)))?;
+        let y_step = (0..self.height)
+            .min_by_key(|t| spread(self.simulate(*t).robots.iter().map(|r| r.position.y)))?;
+
+        let step = math::crt(&[
+            (x_step as i64, self.width as i64),
+            (y_step as i64, self.height as i64),
+        ])
+        .map(|(step, _)| step as isize)
+        .filter(|step| self.simulate(*step).is_tree());
+
+        step.or_else(|| (0..self.period()).find(|t| self.simulate(*t).is_tree()))
+    }
+
     fn is_tree(&self) -> bool {
         let mut positions = HashSet::<Pos>::new();
 
@@ -91,50 +137,64 @@ impl Grid {
 }
 
 fn parse_positions(input: &str) -> Vec<Robot> {
-    fn parse_line(line: &str) -> Robot {
-        let mut parts = line.split([',', ' ']).map(|x| {
-            x.chars()
-                .filter(|x| x.is_ascii_digit() || *x == '-')
-                .collect::<String>()
-                .parse()
-                .unwrap()
-        });
-
-        Robot {
-            position: Pos {
-                x: parts.next().unwrap(),
-                y: parts.next().unwrap(),
-            },
-            velocity: Pos {
-                x: parts.next().unwrap(),
-                y: parts.next().unwrap(),
-            },
-        }
+    fn parse_pos(p: &mut Parser) -> ParseResult<Pos> {
+        let x = p.int()?;
+        p.tag(",")?;
+        let y = p.int()?;
+        Ok(Pos { x, y })
     }
 
-    input.lines().map(parse_line).collect()
+    parse::lines_with(input, |p| {
+        p.tag("p=")?;
+        let position = parse_pos(p)?;
+        p.tag(" v=")?;
+        let velocity = parse_pos(p)?;
+
+        Ok(Robot { position, velocity })
+    })
+    .unwrap_or_else(|e| panic!("Invalid input: {e}"))
+}
+
+fn schema() -> Schema {
+    Schema::new()
+        .bounded("width", 101, 1.., "grid width")
+        .bounded("height", 103, 1.., "grid height")
+        .bounded("seconds", 100, 0.., "seconds simulated for part 1")
+}
+
+fn generate(rng: &mut Rng, args: &Args) -> String {
+    let robots = args.get_or("robots", 500);
+    let width: i64 = args.get_or("width", 101);
+    let height: i64 = args.get_or("height", 103);
+
+    (0..robots)
+        .map(|_| {
+            format!(
+                "p={},{} v={},{}\n",
+                rng.range(0..width),
+                rng.range(0..height),
+                rng.range(1 - width..width),
+                rng.range(1 - height..height),
+            )
+        })
+        .collect()
 }
 
 fn main() {
-    let robots = parse_positions(&common::read_stdin());
+    let args = Args::from_env();
+    if args.run_generate(generate) {
+        return;
+    }
 
-    let mut grid = Grid {
-        width: 101,
-        height: 103,
-        robots,
-    };
+    let params = args.params(&schema());
+    let grid = Grid::new(parse_positions(&common::read_stdin()), &params);
 
-    let (time, safety) = timed(|| grid.simulate(100).count_quadrants());
+    let seconds = params.get("seconds");
+    let (time, safety) = timed(|| grid.simulate(seconds).count_quadrants());
     println!("Part 1: {safety} in {}μs", time.as_micros());
 
-    let (time, iterations) = timed(|| {
-        let mut iterations = 0;
-        while !grid.is_tree() {
-            grid = grid.simulate(1);
-            iterations += 1;
-        }
-        iterations
-    });
+    let (time, iterations) = timed(|| grid.find_tree());
+    let iterations = iterations.map_or("no tree".into(), |x| x.to_string());
     println!("Part 2: {iterations} in {}ms", time.as_millis());
 }
 
@@ -144,16 +204,40 @@ fn main() {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use common::cycle;
+
+    fn example() -> Grid {
+        let params = schema()
+            .resolve(Some(include_str!("../example.cfg")), &Args::default())
+            .unwrap();
+        Grid::new(parse_positions(include_str!("../example.txt")), &params)
+    }
 
     #[test]
     fn part1() {
-        let input = Grid {
-            width: 11,
-            height: 7,
-            robots: parse_positions(include_str!("../example.txt")),
-        };
+        let input = example();
 
         let simulated = input.simulate(100);
         assert_eq!(simulated.count_quadrants(), 12);
     }
+
+    #[test]
+    fn period_test() {
+        let input = example();
+
+        assert_eq!(input.period(), 77);
+
+        let positions = |g: &Grid| g.robots.iter().map(|r| r.position).collect::<Vec<_>>();
+        assert_eq!(positions(&input.simulate(77)), positions(&input));
+
+        let step = |current: &Vec<Pos>| {
+            current
+                .iter()
+                .zip(&input.robots)
+                .map(|(p, r)| input.wrap_position(&(*p + r.velocity)))
+                .collect::<Vec<_>>()
+        };
+        let cycle = cycle::brent(positions(&input), step);
+        assert_eq!((cycle.prefix, cycle.length), (0, 77));
+    }
 }
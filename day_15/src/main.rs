@@ -1,4 +1,4 @@
-use common::{timed, vectors, Grid, Pos};
+use common::{timed, vectors, Args, Grid, Pos, Rng};
 use std::{
     collections::HashSet,
     fmt::{Display, Write},
@@ -157,7 +157,7 @@ impl Map {
                         match_vertical(grid, left_pos, vec),
                         match_vertical(grid, right_pos, vec),
                     );
-                    v.sort_by(|(_, a), (_, b)| b.y.cmp(&a.y));
+                    v.sort_by_key(|(_, a)| std::cmp::Reverse(a.y));
                     v
                 }
                 vectors::DOWN => {
@@ -165,7 +165,7 @@ impl Map {
                         match_vertical(grid, left_pos, vec),
                         match_vertical(grid, right_pos, vec),
                     );
-                    v.sort_by(|(_, a), (_, b)| a.y.cmp(&b.y));
+                    v.sort_by_key(|(_, a)| a.y);
                     v
                 }
 
@@ -347,7 +347,53 @@ fn parse_input(input: &str) -> (Map, Vec<Command>) {
     (parse_map(map), parse_commands(commands))
 }
 
+fn generate(rng: &mut Rng, args: &Args) -> String {
+    let width = args.get_or("width", 50);
+    let height = args.get_or("height", width);
+    let moves = args.get_or("moves", 20000);
+
+    let mut grid = (0..height)
+        .map(|y| {
+            (0..width)
+                .map(|x| {
+                    if x == 0 || y == 0 || x == width - 1 || y == height - 1 || rng.chance(0.05) {
+                        '#'
+                    } else if rng.chance(0.3) {
+                        'O'
+                    } else {
+                        '.'
+                    }
+                })
+                .collect::<Vec<_>>()
+        })
+        .collect::<Vec<_>>();
+    grid[height / 2][width / 2] = '@';
+
+    let grid = grid
+        .into_iter()
+        .map(|row| row.into_iter().chain(['\n']).collect::<String>())
+        .collect::<String>();
+
+    let moves = (0..moves)
+        .map(|i| {
+            let c = *rng.choose(&['<', '>', '^', 'v']);
+            if (i + 1) % 1000 == 0 {
+                format!("{c}\n")
+            } else {
+                c.to_string()
+            }
+        })
+        .collect::<String>();
+
+    grid + "\n" + moves.trim_end() + "\n"
+}
+
 fn main() {
+    let args = Args::from_env();
+    if args.run_generate(generate) {
+        return;
+    }
+
     let (map, commands) = parse_input(&common::read_stdin());
 
     let (time, small) = timed(|| map.clone().run_all(&commands));
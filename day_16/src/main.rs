@@ -1,8 +1,12 @@
-use common::{ vectors, timed };
+use common::{
+    bits::{BitGrid, BitSet},
+    params::Schema,
+    queue::{Queue, QueueKind},
+    search::{self, ShortestPaths},
+    timed, vectors, Args, Rng,
+};
 use std::{fmt::Display, hash::Hash};
 
-type HashSet<T> = std::collections::HashSet<T>;
-
 use common::Pos;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
@@ -95,6 +99,15 @@ impl Heading {
         }
     }
 
+    fn vector(&self) -> Pos {
+        match self {
+            Self::North => vectors::UP,
+            Self::South => vectors::DOWN,
+            Self::East => vectors::RIGHT,
+            Self::West => vectors::LEFT,
+        }
+    }
+
     fn from_pos(start: Pos, end: Pos) -> Self {
         let vec = end - start;
         match vec {
@@ -118,7 +131,23 @@ impl Hash for Vector {
     }
 }
 
-fn find_paths_a_star(m @ Map { start, end, .. }: &Map) -> Vec<Vec<Vector>> {
+const HEADINGS: [Heading; 4] = [Heading::North, Heading::South, Heading::East, Heading::West];
+
+fn state_index(width: isize, v: Vector) -> usize {
+    (v.pos.y * width + v.pos.x) as usize * 4 + v.heading as usize
+}
+
+/// Lowest scores from the start facing east, exact for every state scoring
+/// no more than the best way into the end
+struct Scores {
+    scores: ShortestPaths,
+    width: isize,
+    end: Pos,
+    best: u64,
+}
+
+/// Searches until the lowest scoring way into the end is settled
+fn find_scores(m @ Map { start, end, grid }: &Map, queue: QueueKind) -> Scores {
     fn get_successors(current: Vector, map: &Map) -> Vec<(Vector, u64)> {
         let mut succ = vec![];
 
@@ -140,124 +169,94 @@ fn find_paths_a_star(m @ Map { start, end, .. }: &Map) -> Vec<Vec<Vector>> {
         succ
     }
 
-    fn heuristic(end: &Pos, node: &Pos) -> u64 {
-        node.distance(end) as u64
-    }
+    let index = |v: Vector| state_index(grid.width, v);
+    let vector = |i: usize| Vector {
+        pos: Pos::new((i / 4) as isize % grid.width, (i / 4) as isize / grid.width),
+        heading: HEADINGS[i % 4],
+    };
 
-    let results = pathfinding::directed::astar::astar_bag(
-        &Vector {
-            pos: *start,
-            heading: Heading::East,
+    // Two turns and a step is the most expensive move
+    let len = (grid.width * grid.height) as usize * 4;
+    let start = Vector {
+        pos: *start,
+        heading: Heading::East,
+    };
+    let end_cell = (end.y * grid.width + end.x) as usize;
+    let scores = search::dijkstra(
+        Queue::new(queue, len, 2001),
+        len,
+        index(start),
+        |i| {
+            get_successors(vector(i), m)
+                .into_iter()
+                .map(|(v, score)| (index(v), score))
         },
-        |state| get_successors(*state, m),
-        |state| heuristic(end, &state.pos),
-        |x| x.pos == *end,
+        |i| i / 4 == end_cell,
     );
 
-    match results {
-        Some((paths, ..)) => paths.into_iter().collect(),
-        None => panic!("no paths found"),
+    let best = HEADINGS
+        .iter()
+        .filter_map(|heading| {
+            scores.distance(index(Vector {
+                pos: *end,
+                heading: *heading,
+            }))
+        })
+        .min()
+        .expect("no paths found");
+
+    Scores {
+        scores,
+        width: grid.width,
+        end: *end,
+        best,
     }
-
-    // fn reconstruct_path(came_from: &HashMap<Vector, Vector>, mut current: Vector) -> Vec<Vector> {
-    //     let mut total_path = vec![current];
-
-    //     while came_from.contains_key(&current) {
-    //         current = came_from[&current];
-    //         total_path.push(current);
-    //     }
-
-    //     total_path.reverse();
-    //     total_path
-    // }
-
-    // fn min_score_node(set: &HashSet<Vector>, score: &HashMap<Vector, f64>) -> Vector {
-    //     let mut min_node = Vector::default();
-    //     let mut min_score = f64::MAX;
-    //     for v in set.iter() {
-    //         if score[v] < min_score {
-    //             min_node = *v;
-    //             min_score = score[v];
-    //         }
-    //     }
-
-    //     min_node
-    // }
-
-    // let v = Vector {
-    //     pos: *start,
-    //     heading: Heading::East,
-    // };
-    // let mut open_set = HashSet::from_iter([v]);
-
-    // let mut came_from = HashMap::<Vector, Vector>::new();
-    // let mut g_score = HashMap::from_iter([(v, 0.0)]);
-    // let mut f_score = HashMap::from_iter([(v, heuristic(end, start))]);
-
-    // let mut paths = vec![];
-
-    // while !open_set.is_empty() {
-    //     let current = min_score_node(&open_set, &f_score);
-    //     if current.pos == *end {
-    //         paths.push(reconstruct_path(&came_from, current));
-    //     }
-
-    //     open_set.remove(&current);
-    //     grid.iter_adjacent_cardinal(current.pos)
-    //         .filter(|(b, _)| matches!(b, Block::Empty | Block::End))
-    //         .for_each(|(b, p)| {
-    //             let heading = Heading::from_pos(current.pos, p);
-    //             let num_rotations = current.heading.num_rotations(&heading);
-
-    //             let v = Vector { pos: p, heading };
-
-    //             let current_g_score = g_score.get(&current).unwrap_or(&f64::MAX);
-    //             let possible_g_score = current_g_score
-    //                 + current.pos.distance(&p)
-    //                 + (1000 * num_rotations as u64) as f64;
-
-    //             if possible_g_score < *g_score.get(&v).unwrap_or(&f64::MAX) {
-    //                 came_from.insert(v, current);
-    //                 g_score.insert(v, possible_g_score);
-    //                 f_score.insert(v, possible_g_score + heuristic(end, &p));
-    //                 if !open_set.contains(&v) {
-    //                     open_set.insert(v);
-    //                 }
-    //             }
-    //         })
-    // }
-
-    // paths
 }
+impl Scores {
+    fn get(&self, v: Vector) -> Option<u64> {
+        self.scores.distance(state_index(self.width, v))
+    }
 
-fn count_path(path: &[Vector]) -> u64 {
-    let mut total = 0;
-
-    for i in 1..path.len() {
-        let prev = path[i - 1].heading;
-        let cur = path[i].heading;
+    /// Tiles on any lowest scoring path. Walks back from the end along moves
+    /// that keep the score tight, visiting each state once
+    fn tiles(&self, grid: &Grid) -> BitGrid {
+        let mut tiles = BitGrid::new(grid.width, grid.height);
+        let mut seen = BitSet::new();
+        let mut stack = HEADINGS
+            .iter()
+            .map(|heading| Vector {
+                pos: self.end,
+                heading: *heading,
+            })
+            .filter(|v| self.get(*v) == Some(self.best))
+            .collect::<Vec<_>>();
 
-        total += 1000 * prev.num_rotations(&cur) as u64;
-    }
+        while let Some(v) = stack.pop() {
+            if !seen.insert(state_index(self.width, v)) {
+                continue;
+            }
+            tiles.insert(v.pos);
 
-    total + path.len() as u64 - 1
-}
+            let to = self.get(v).expect("only scored states are walked");
+            let back = v.pos - v.heading.vector();
+            if !grid.is_inside(&back) {
+                continue;
+            }
+            for heading in HEADINGS {
+                let u = Vector { pos: back, heading };
+                let step = 1 + 1000 * heading.num_rotations(&v.heading) as u64;
+                if self.get(u).is_some_and(|from| from + step == to) {
+                    stack.push(u);
+                }
+            }
+        }
 
-fn count_tiles<'a, I, U>(paths: I) -> usize 
-    where 
-        I: Iterator<Item = U>,
-        U: IntoIterator<Item = &'a Vector>
-{
-    let positions: HashSet<_> = paths
-        .flatten()
-        .map(|x| x.pos)
-        .collect();
-    
-    positions.len()
+        tiles
+    }
 }
 
 #[allow(unused)]
-fn print_paths(map: &Map, paths: &Vec<Vec<Vector>>) {
+fn print_tiles(map: &Map, tiles: &BitGrid) {
     let mut grid = common::Grid {
         height: map.grid.height,
         width: map.grid.width,
@@ -273,32 +272,91 @@ fn print_paths(map: &Map, paths: &Vec<Vec<Vector>>) {
             .collect(),
     };
 
-    for path in paths {
-        for p in path.iter() {
-            grid[p.pos] = match p.heading {
-                Heading::North => '^',
-                Heading::South => 'v',
-                Heading::East => '>',
-                Heading::West => '<',
+    for p in tiles.iter() {
+        grid[p] = 'O';
+    }
+
+    println!("{grid}")
+}
+
+fn generate(rng: &mut Rng, args: &Args) -> String {
+    // Mazes are carved on odd coordinates, so the size must be odd
+    let size = args.get_or("size", 141usize) | 1;
+    let loops = args.get_or("loops", 0.05);
+
+    let mut grid = vec![vec!['#'; size]; size];
+    let start = Pos::new(1, size as isize - 2);
+    let mut stack = vec![start];
+    grid[start.y as usize][start.x as usize] = '.';
+
+    let is_carvable = |grid: &Vec<Vec<char>>, p: Pos| {
+        p.x > 0
+            && p.y > 0
+            && p.x < size as isize - 1
+            && p.y < size as isize - 1
+            && grid[p.y as usize][p.x as usize] == '#'
+    };
+
+    while let Some(&current) = stack.last() {
+        let next = vectors::CARDINAL
+            .iter()
+            .map(|v| (current + *v, current + *v * 2))
+            .filter(|(_, p)| is_carvable(&grid, *p))
+            .collect::<Vec<_>>();
+
+        if next.is_empty() {
+            stack.pop();
+        } else {
+            let (wall, cell) = *rng.choose(&next);
+            grid[wall.y as usize][wall.x as usize] = '.';
+            grid[cell.y as usize][cell.x as usize] = '.';
+            stack.push(cell);
+        }
+    }
+
+    // Knock out extra walls so there is more than one best path
+    #[allow(clippy::needless_range_loop)]
+    for y in 1..size - 1 {
+        for x in 1..size - 1 {
+            if (x + y) % 2 == 1 && rng.chance(loops) {
+                grid[y][x] = '.';
             }
         }
     }
 
-    println!("{grid}")
+    grid[start.y as usize][start.x as usize] = 'S';
+    grid[1][size - 2] = 'E';
+
+    let grid = grid
+        .into_iter()
+        .map(|row| row.into_iter().collect::<String>())
+        .collect::<Vec<_>>();
+    grid.join("\n") + "\n"
+}
+
+fn schema() -> Schema {
+    Schema::new().param("queue", QueueKind::Bucket, "binary, indexed or bucket")
 }
 
 fn main() {
+    let args = Args::from_env();
+    if args.run_generate(generate) {
+        return;
+    }
+
+    let params = args.params(&schema());
+    let queue = params.get("queue");
+
     let map = parse_map(&common::read_stdin());
-    let (time, path) = timed(|| find_paths_a_star(&map));
-    let distance = count_path(&path[0]);
-    println!("Part 1: {distance} in {}ms", time.as_millis());
+    let (time, scores) = timed(|| find_scores(&map, queue));
+    println!("Part 1: {} in {}ms", scores.best, time.as_millis());
 
-    let (time, tiles) = timed(|| count_tiles(path.iter()));
+    let (time, tiles) = timed(|| scores.tiles(&map.grid).len());
     println!("Part 2: {tiles} in {}ms", time.as_millis());
 }
 
-// Part 1: 90460 in 44ms
-// Part 2: 575 in 17ms
+// Part 1: 90460 in 3ms
+// Part 2: 575 in 0ms
 
 #[cfg(test)]
 mod tests {
@@ -326,8 +384,9 @@ mod tests {
 
         let map = parse_map(input);
         assert_eq!(map.grid.to_string(), input);
-        let paths = find_paths_a_star(&map);
-        assert_eq!(count_path(&paths[0]), 7036);
+        let scores = find_scores(&map, QueueKind::Bucket);
+        assert_eq!(scores.best, 7036);
+        assert_eq!(scores.tiles(&map.grid).len(), 45);
 
         let input = "\
             #################\n\
@@ -351,12 +410,13 @@ mod tests {
 
         let map = parse_map(input);
         assert_eq!(map.grid.to_string(), input);
-        let paths = &find_paths_a_star(&map);
-        print_paths(&map, paths);
-        assert_eq!(count_path(&paths[0]), 11048);
-
-        assert_eq!(count_path(&paths[0]), count_path(&paths[1]));
+        for queue in [QueueKind::Binary, QueueKind::Indexed, QueueKind::Bucket] {
+            let scores = find_scores(&map, queue);
+            assert_eq!(scores.best, 11048);
 
-        assert_eq!(count_tiles(paths.iter()), 64);
+            let tiles = scores.tiles(&map.grid);
+            print_tiles(&map, &tiles);
+            assert_eq!(tiles.len(), 64);
+        }
     }
 }
@@ -1,4 +1,7 @@
-use common::timed;
+use common::{
+    parse::{self, ParseResult, Parser},
+    timed, Args, Rng,
+};
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 enum Register {
@@ -162,34 +165,29 @@ impl Machine {
 }
 
 fn parse_input(input: &str) -> (Machine, Vec<u8>) {
-    let mut lines = input.lines();
-
-    fn parse_register(line: &str) -> u64 {
-        line.split_once(':')
-            .unwrap()
-            .1
-            .trim_ascii()
-            .parse()
-            .unwrap()
+    fn parse_register(p: &mut Parser, name: &str) -> ParseResult<u64> {
+        p.tag("Register ")?;
+        p.tag(name)?;
+        p.tag(":")?;
+        p.spaces();
+        let value = p.int()?;
+        p.newline()?;
+        Ok(value)
     }
 
-    fn parse_program(line: &str) -> Vec<u8> {
-        line.split_once(':')
-            .unwrap()
-            .1
-            .trim_ascii()
-            .split(',')
-            .map(|x| x.parse().unwrap())
-            .collect()
-    }
+    parse::parse_all(input, |p| {
+        let a = parse_register(p, "A")?;
+        let b = parse_register(p, "B")?;
+        let c = parse_register(p, "C")?;
+        p.whitespace();
 
-    let a = parse_register(lines.next().unwrap());
-    let b = parse_register(lines.next().unwrap());
-    let c = parse_register(lines.next().unwrap());
-    lines.next().unwrap();
-    let program = parse_program(lines.next().unwrap());
+        p.tag("Program:")?;
+        p.spaces();
+        let program = p.separated(",", |p| p.int())?;
 
-    (Machine::with_registers(Registers { a, b, c }), program)
+        Ok((Machine::with_registers(Registers { a, b, c }), program))
+    })
+    .unwrap_or_else(|e| panic!("Invalid input: {e}"))
 }
 
 fn join_output(output: &[u8]) -> String {
@@ -207,7 +205,20 @@ fn join_output(output: &[u8]) -> String {
         })
 }
 
+fn generate(rng: &mut Rng, _args: &Args) -> String {
+    // `part2` is transpiled from this program, so only the register varies
+    let a = rng.below(1 << 48);
+    format!(
+        "Register A: {a}\nRegister B: 0\nRegister C: 0\n\nProgram: 2,4,1,1,7,5,1,5,4,0,0,3,5,5,3,0\n"
+    )
+}
+
 fn main() {
+    let args = Args::from_env();
+    if args.run_generate(generate) {
+        return;
+    }
+
     let (machine, program) = parse_input(&common::read_stdin());
 
     let (time, output) = timed(|| {
@@ -292,6 +303,30 @@ fn part2(a: u64) -> Vec<u8> {
 #[cfg(test)]
 mod tests {
     use crate::*;
+    use common::{differential::shrink_int, Differential};
+
+    #[test]
+    fn differential_test() {
+        const PROGRAM: [u8; 16] = [2, 4, 1, 1, 7, 5, 1, 5, 4, 0, 0, 3, 5, 5, 3, 0];
+
+        let d = Differential::new()
+            .register("machine", |a: &u64| {
+                Machine::with_registers(Registers {
+                    a: *a,
+                    ..Default::default()
+                })
+                .run(&PROGRAM)
+                .output
+            })
+            .register("transpiled", |a: &u64| part2(*a));
+
+        // `part2` skips the loop entirely when A starts at 0, so start from 1
+        let shrink = |a: &u64| shrink_int(*a).into_iter().filter(|x| *x > 0).collect();
+        let result = d.check_generated(0..200, |rng| 1 + rng.below(1 << 48), shrink);
+        if let Err(e) = result {
+            panic!("{e}");
+        }
+    }
 
     #[test]
     fn program_test() {
@@ -1,8 +1,12 @@
-use common::timed;
-use std::fmt::Display;
+use common::{
+    dsu::GridDsu,
+    params::Schema,
+    queue::{Queue, QueueKind},
+    search, timed, Args, Rng,
+};
+use std::{collections::HashMap, fmt::Display};
 
 use common::Pos;
-use pathfinding::directed::astar::astar;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 enum Block {
@@ -30,31 +34,19 @@ fn add_bytes_to_grid(mut grid: Grid, incoming: &[Pos], bytes: usize) -> Grid {
     grid
 }
 
-fn find_path(grid: &Grid) -> Option<Vec<Pos>> {
+fn find_path(grid: &Grid, queue: QueueKind) -> Option<Vec<Pos>> {
     let start = Pos { x: 0, y: 0 };
     let end = Pos {
         x: grid.width - 1,
         y: grid.height - 1,
     };
 
-    fn heuristic(a: Pos, b: Pos) -> u64 {
-        a.distance(&b) as u64
-    }
-
-    fn successors(grid: &Grid, node: Pos) -> Vec<(Pos, u64)> {
-        grid.iter_adjacent_cardinal(node)
-            .filter(|(b, _)| matches!(b, Block::Safe))
-            .map(|(_, p)| (p, 1))
-            .collect()
-    }
+    let queue = Queue::new(queue, (grid.width * grid.height) as usize, 1);
+    let paths = search::grid_dijkstra(grid, queue, start, Some(end), |b| {
+        matches!(b, Block::Safe).then_some(1)
+    });
 
-    astar(
-        &start,
-        |state| successors(grid, *state),
-        |state| heuristic(*state, end),
-        |state| *state == end,
-    )
-    .map(|x| x.0)
+    paths.path_to(&end)
 }
 
 fn parse_input(input: &str) -> Vec<Pos> {
@@ -69,50 +61,112 @@ fn parse_input(input: &str) -> Vec<Pos> {
 }
 
 fn get_first_blocking(mut grid: Grid, incoming: &[Pos]) -> Pos {
-    fn find_path_bfs(grid: &Grid) -> Option<Vec<Pos>> {
-        use pathfinding::directed::bfs::bfs;
+    let start = Pos { x: 0, y: 0 };
+    let end = Pos {
+        x: grid.width - 1,
+        y: grid.height - 1,
+    };
 
-        let start = Pos { x: 0, y: 0 };
-        let end = Pos {
-            x: grid.width - 1,
-            y: grid.height - 1,
-        };
+    // Work backwards from every byte fallen, clearing them until the corners
+    // join up. The byte that joined them is the first to block the path.
+    // Bytes landing on corrupted cells, including ones corrupted before
+    // `incoming`, are never cleared
+    let mut first_fallen = HashMap::new();
+    for (i, p) in incoming.iter().enumerate() {
+        if grid[*p] == Block::Safe {
+            first_fallen.insert(*p, i);
+            grid[*p] = Block::Corrupted;
+        }
+    }
+
+    fn join(grid: &Grid, sets: &mut GridDsu, p: Pos) {
+        for (b, q) in grid.iter_adjacent_cardinal(p) {
+            if *b == Block::Safe {
+                sets.union(p, q);
+            }
+        }
+    }
 
-        fn successors(grid: &Grid, node: Pos) -> Vec<Pos> {
-            grid.iter_adjacent_cardinal(node)
-                .filter(|(b, _)| matches!(b, Block::Safe))
-                .map(|(_, p)| p)
-                .collect()
+    let mut sets = GridDsu::new(grid.width, grid.height);
+    for (b, p) in grid.iter() {
+        if *b == Block::Safe {
+            join(&grid, &mut sets, p);
         }
+    }
 
-        bfs(&start, |x| successors(grid, *x), |x| *x == end)
+    if sets.same(start, end) {
+        panic!("all clear")
     }
 
-    for p in incoming {
-        grid[*p] = Block::Corrupted;
+    for (i, p) in incoming.iter().enumerate().rev() {
+        if first_fallen.get(p) != Some(&i) {
+            continue;
+        }
 
-        if find_path_bfs(&grid).is_none() {
+        grid[*p] = Block::Safe;
+        join(&grid, &mut sets, *p);
+
+        if sets.same(start, end) {
             return *p;
         }
     }
 
-    panic!("all clear")
+    panic!("never connected")
+}
+
+fn schema() -> Schema {
+    Schema::new()
+        .bounded("size", 71usize, 2.., "width and height of the memory space")
+        .param("fallen", 1024usize, "bytes fallen before part 1")
+        .param("queue", QueueKind::Bucket, "binary, indexed or bucket")
+}
+
+fn generate(rng: &mut Rng, args: &Args) -> String {
+    let size = args.get_or("size", 71usize);
+    let bytes = args.get_or("bytes", 3450usize);
+
+    let mut cells = (0..size)
+        .flat_map(|y| (0..size).map(move |x| (x, y)))
+        .filter(|&p| p != (0, 0) && p != (size - 1, size - 1))
+        .collect::<Vec<_>>();
+    rng.shuffle(&mut cells);
+
+    cells
+        .into_iter()
+        .take(bytes)
+        .map(|(x, y)| format!("{x},{y}\n"))
+        .collect()
 }
 
 fn main() {
-    let grid = make_grid(71, 71);
+    let args = Args::from_env();
+    if args.run_generate(generate) {
+        return;
+    }
+
+    let params = args.params(&schema());
+    let size = params.get("size");
+    let fallen = params.get("fallen");
+    let queue = params.get("queue");
+
+    let grid = make_grid(size, size);
     let incoming = parse_input(&common::read_stdin());
+    assert!(
+        fallen <= incoming.len(),
+        "--fallen {fallen} but only {} bytes in the input",
+        incoming.len()
+    );
 
     let (time, (blocked_grid, path)) = timed(|| {
-        let blocked_grid = add_bytes_to_grid(grid, &incoming, 1024);
-        let path = find_path(&blocked_grid).unwrap();
+        let blocked_grid = add_bytes_to_grid(grid, &incoming, fallen);
+        let path = find_path(&blocked_grid, queue).unwrap();
 
         (blocked_grid, path)
     });
 
     println!("Part 1: {} in {}μs", path.len() - 1, time.as_micros());
 
-    let (time, first_blocking) = timed(|| get_first_blocking(blocked_grid, &incoming[1024..]));
+    let (time, first_blocking) = timed(|| get_first_blocking(blocked_grid, &incoming[fallen..]));
     println!(
         "Part 2: {},{} in {}ms",
         first_blocking.x,
@@ -195,7 +249,8 @@ mod tests {
         println!("{}", grid);
         println!();
 
-        let path = find_path(&grid).unwrap();
+        // Heaps break ties by position, which gives the drawn path
+        let path = find_path(&grid, QueueKind::Binary).unwrap();
         let path_string = get_path_grid(&grid, &path);
 
         let expected_path = "\
@@ -211,9 +266,24 @@ mod tests {
         assert_eq!(path_string, expected_path);
         assert_eq!(path.len() - 1, 22);
 
+        for queue in [QueueKind::Indexed, QueueKind::Bucket] {
+            assert_eq!(find_path(&grid, queue).map(|p| p.len()), Some(path.len()));
+        }
+
         assert_eq!(
-            get_first_blocking(grid, &incoming[12..]),
+            get_first_blocking(grid.clone(), &incoming[12..]),
             Pos { x: 6, y: 1 }
         );
+
+        // Bytes falling again where one already fell change nothing
+        for repeat in &incoming[..12] {
+            let mut later = incoming[12..].to_vec();
+            later.push(*repeat);
+            assert_eq!(
+                get_first_blocking(grid.clone(), &later),
+                Pos { x: 6, y: 1 },
+                "{repeat}"
+            );
+        }
     }
 }
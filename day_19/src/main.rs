@@ -1,4 +1,4 @@
-use common::timed;
+use common::{timed, Args, Rng};
 use std::collections::HashMap;
 
 fn parse_input(input: &str) -> (Vec<String>, Vec<String>) {
@@ -17,19 +17,49 @@ fn parse_input(input: &str) -> (Vec<String>, Vec<String>) {
     )
 }
 
-fn main() {
-    let (towels, designs) = parse_input(&common::read_stdin());
+fn generate(rng: &mut Rng, args: &Args) -> String {
+    const COLOURS: [char; 5] = ['w', 'u', 'b', 'r', 'g'];
 
-    let (time, possible) = timed(|| {
-        let regex = {
-            let re = towels.join("|");
+    let towels = args.get_or("towels", 400);
+    let designs = args.get_or("designs", 400);
 
-            let re = format!("^(?:{re})+$");
-            regex::Regex::new(&re).unwrap()
-        };
+    let mut towel_set = std::collections::BTreeSet::new();
+    while towel_set.len() < towels {
+        let len = rng.range(1..9);
+        towel_set.insert((0..len).map(|_| *rng.choose(&COLOURS)).collect::<String>());
+    }
+    let towels = towel_set.into_iter().collect::<Vec<_>>();
 
-        designs.iter().filter(|x| regex.is_match(x)).count()
+    // Most designs are built from towels, the rest are random and likely impossible
+    let designs = (0..designs).map(|_| {
+        let len = rng.range(40..61) as usize;
+        let mut design = String::new();
+        let possible = rng.chance(0.7);
+
+        while design.len() < len {
+            if possible {
+                let towel: &String = rng.choose(&towels);
+                design.push_str(towel);
+            } else {
+                design.push(*rng.choose(&COLOURS));
+            }
+        }
+
+        design + "\n"
     });
+
+    towels.join(", ") + "\n\n" + &designs.collect::<String>()
+}
+
+fn main() {
+    let args = Args::from_env();
+    if args.run_generate(generate) {
+        return;
+    }
+
+    let (towels, designs) = parse_input(&common::read_stdin());
+
+    let (time, possible) = timed(|| count_matching_designs(&towels, &designs));
     println!("Part 1: {possible} in {}ms", time.as_millis());
 
     let (time, combinations) = timed(|| count_possible_designs(&towels, &designs));
@@ -39,6 +69,17 @@ fn main() {
 // Part 1: 306 in 8ms
 // Part 2: 604622004681855 in 57ms
 
+fn count_matching_designs(towels: &[String], designs: &[String]) -> usize {
+    let regex = {
+        let re = towels.join("|");
+
+        let re = format!("^(?:{re})+$");
+        regex::Regex::new(&re).unwrap()
+    };
+
+    designs.iter().filter(|x| regex.is_match(x)).count()
+}
+
 fn count_possible_designs(towels: &[String], designs: &[String]) -> usize {
     fn count_possible<'a>(
         towels: &[String],
@@ -72,6 +113,37 @@ fn count_possible_designs(towels: &[String], designs: &[String]) -> usize {
 mod tests {
     use super::*;
 
+    #[test]
+    fn differential_test() {
+        use common::{differential::shrink_vec, Differential};
+
+        type Input = (Vec<String>, Vec<String>);
+
+        let d = Differential::new()
+            .register("regex", |(towels, designs): &Input| {
+                count_matching_designs(towels, designs)
+            })
+            .register("counting", |(towels, designs): &Input| {
+                designs
+                    .iter()
+                    .filter(|x| count_possible_designs(towels, &[x.to_string()]) > 0)
+                    .count()
+            });
+
+        let args = Args::parse(["--towels", "30", "--designs", "20"]);
+        let shrink = |(towels, designs): &Input| {
+            let fewer_designs = shrink_vec(designs).into_iter().map(|d| (towels.clone(), d));
+            let fewer_towels = shrink_vec(towels).into_iter().map(|t| (t, designs.clone()));
+
+            fewer_designs.chain(fewer_towels).collect()
+        };
+
+        let result = d.check_generated(0..20, |rng| parse_input(&generate(rng, &args)), shrink);
+        if let Err(e) = result {
+            panic!("{e}");
+        }
+    }
+
     #[test]
     fn find_prefixes_test() {
         let towels = [
@@ -1,44 +1,229 @@
-use common::{timed, timed_repeated};
-use std::io::Read as _;
-
-fn is_safe_dampened(seq: &[i32]) -> bool {
-    if is_safe(seq) {
-        true
-    } else {
-        (0..seq.len()).any(|i| {
-            let mut seq = seq.to_vec();
-            seq.remove(i);
-            is_safe(&seq)
+use common::{params::Schema, timed, timed_repeated, Args, Rng};
+use std::{fmt::Display, io::Read as _, str::FromStr};
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+enum Direction {
+    Increasing,
+    Decreasing,
+    /// Either increasing or decreasing throughout
+    #[default]
+    Either,
+    /// Steps may go either way
+    Any,
+}
+impl FromStr for Direction {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "increasing" => Ok(Self::Increasing),
+            "decreasing" => Ok(Self::Decreasing),
+            "either" => Ok(Self::Either),
+            "any" => Ok(Self::Any),
+            x => Err(format!(
+                "Unknown direction {x:?}, expected increasing, decreasing, either or any"
+            )),
+        }
+    }
+}
+impl Display for Direction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Increasing => "increasing",
+            Self::Decreasing => "decreasing",
+            Self::Either => "either",
+            Self::Any => "any",
         })
     }
 }
 
-fn is_safe(seq: &[i32]) -> bool {
-    let mut last_change = i32::MIN;
+/// What makes a report safe: every step between kept levels is between
+/// `min_step` and `max_step` in size and goes in `direction`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Rules {
+    min_step: i32,
+    max_step: i32,
+    direction: Direction,
+}
+impl Default for Rules {
+    fn default() -> Self {
+        Self {
+            min_step: 1,
+            max_step: 3,
+            direction: Direction::Either,
+        }
+    }
+}
+impl Rules {
+    fn step_ok(&self, direction: Direction, from: i32, to: i32) -> bool {
+        let diff = to - from;
+        let steps = self.min_step..=self.max_step;
 
-    fn is_error(diff: i32, last_change: i32) -> bool {
-        const SIGN_WIDTH: usize = i32::BITS as usize - 1;
+        match direction {
+            Direction::Increasing => steps.contains(&diff),
+            Direction::Decreasing => steps.contains(&-diff),
+            Direction::Any | Direction::Either => steps.contains(&diff.abs()),
+        }
+    }
 
-        let sign_changed =
-            last_change != i32::MIN && (last_change >> SIGN_WIDTH) != (diff >> SIGN_WIDTH);
+    /// Directions to try, [`Direction::Either`] being either of the strict ones
+    fn directions(&self) -> &[Direction] {
+        match self.direction {
+            Direction::Either => &[Direction::Increasing, Direction::Decreasing],
+            ref x => std::slice::from_ref(x),
+        }
+    }
 
-        diff == 0 || diff.abs() > 3 || sign_changed
+    fn is_safe(&self, seq: &[i32]) -> bool {
+        self.directions()
+            .iter()
+            .any(|d| seq.windows(2).all(|w| self.step_ok(*d, w[0], w[1])))
     }
 
-    for i in 1..seq.len() {
-        let diff = seq[i] - seq[i - 1];
+    /// Whether removing at most `tolerance` levels makes `seq` safe
+    fn tolerates(&self, seq: &[i32], tolerance: usize) -> bool {
+        const STACK: usize = 32;
+        if self.is_safe(seq) {
+            return true;
+        }
+        if seq.len() > STACK {
+            return self.culprits(seq, tolerance).is_some();
+        }
+
+        let (mut removed, mut previous) = ([0; STACK], [0; STACK]);
+        self.directions().iter().any(|d| {
+            let removed = &mut removed[..seq.len()];
+            let previous = &mut previous[..seq.len()];
+            self.best_chain(*d, seq, tolerance, removed, previous)
+                .is_some()
+        })
+    }
+
+    /// Fewest levels to remove, in ascending order, for `seq` to be safe, or
+    /// `None` if that takes more than `tolerance`. Ties prefer removing earlier
+    /// levels
+    fn culprits(&self, seq: &[i32], tolerance: usize) -> Option<Vec<usize>> {
+        if seq.is_empty() {
+            return Some(vec![]);
+        }
+
+        let mut removed = vec![0; seq.len()];
+        let mut previous = vec![0; seq.len()];
 
-        if is_error(diff, last_change) {
-            return false;
+        self.directions()
+            .iter()
+            .filter_map(|d| {
+                let (last, count) =
+                    self.best_chain(*d, seq, tolerance, &mut removed, &mut previous)?;
+
+                let mut kept = vec![false; seq.len()];
+                let mut i = last;
+                while i != usize::MAX {
+                    kept[i] = true;
+                    i = previous[i];
+                }
+
+                let culprits = (0..seq.len()).filter(|i| !kept[*i]).collect::<Vec<_>>();
+                debug_assert_eq!(culprits.len(), count);
+                Some(culprits)
+            })
+            .min_by_key(|x| x.len())
+    }
+
+    /// Longest chain of levels with valid steps in `direction`, skipping at
+    /// most `tolerance` levels in total. Fills `removed[i]` with the fewest
+    /// levels skipped before `i` by a chain keeping `i`, and `previous[i]` with
+    /// the level kept before it, then returns the chain's last level and how
+    /// many levels it skips. Each level only looks back `tolerance + 1`
+    /// levels, so this is O(n·k)
+    fn best_chain(
+        &self,
+        direction: Direction,
+        seq: &[i32],
+        tolerance: usize,
+        removed: &mut [usize],
+        previous: &mut [usize],
+    ) -> Option<(usize, usize)> {
+        for i in 0..seq.len() {
+            removed[i] = if i <= tolerance { i } else { usize::MAX };
+            previous[i] = usize::MAX;
+
+            for j in (i.saturating_sub(tolerance + 1)..i).rev() {
+                let total = removed[j].saturating_add(i - j - 1);
+                if total <= tolerance
+                    && total < removed[i]
+                    && self.step_ok(direction, seq[j], seq[i])
+                {
+                    removed[i] = total;
+                    previous[i] = j;
+                }
+            }
         }
 
-        last_change = diff;
+        // Levels after the last one kept are removed too
+        (0..seq.len())
+            .rev()
+            .map(|i| (i, removed[i].saturating_add(seq.len() - 1 - i)))
+            .filter(|(_, total)| *total <= tolerance)
+            .min_by_key(|(_, total)| *total)
     }
+}
 
-    true
+fn schema() -> Schema {
+    Schema::new()
+        .bounded("tolerance", 1usize, 0.., "levels part 2 may remove")
+        .bounded("min-step", 1, 0.., "smallest allowed step")
+        .bounded("max-step", 3, 0.., "largest allowed step")
+        .param(
+            "direction",
+            Direction::Either,
+            "increasing, decreasing, either or any",
+        )
+        .option("explain", "list the levels to remove from each report")
+}
+
+fn generate(rng: &mut Rng, args: &Args) -> String {
+    let reports = args.get_or("reports", 1000);
+
+    (0..reports)
+        .map(|_| {
+            let direction = if rng.chance(0.5) { 1 } else { -1 };
+            let mut level = rng.range(40..60);
+            let mut levels = vec![level];
+
+            for _ in 1..rng.range(5..9) {
+                level += if rng.chance(0.1) {
+                    rng.range(-4..5)
+                } else {
+                    direction * rng.range(1..4)
+                };
+                levels.push(level);
+            }
+
+            let levels = levels.iter().map(|x| x.to_string()).collect::<Vec<_>>();
+            levels.join(" ") + "\n"
+        })
+        .collect()
 }
 
 fn main() {
+    let args = Args::from_env();
+    if args.run_generate(generate) {
+        return;
+    }
+
+    let params = args.params(&schema());
+    let tolerance = params.get("tolerance");
+    let rules = Rules {
+        min_step: params.get("min-step"),
+        max_step: params.get("max-step"),
+        direction: params.get("direction"),
+    };
+    assert!(
+        rules.min_step <= rules.max_step,
+        "--min-step is larger than --max-step"
+    );
+
     let input = {
         let mut buf = String::new();
         std::io::stdin().read_to_string(&mut buf).unwrap();
@@ -56,12 +241,34 @@ fn main() {
     });
     println!("Time to parse: {}μs", time_to_parse.as_micros());
 
-    let (time, safe_count) = timed_repeated(10, || lines.iter().filter(|x| is_safe(x)).count());
+    let (time, safe_count) =
+        timed_repeated(10, || lines.iter().filter(|x| rules.is_safe(x)).count());
     println!("Part 1: {safe_count} in {}μs", time.as_micros());
 
-    let (time, safe_count) =
-        timed_repeated(10, || lines.iter().filter(|x| is_safe_dampened(x)).count());
+    let (time, safe_count) = timed_repeated(10, || {
+        lines
+            .iter()
+            .filter(|x| rules.tolerates(x, tolerance))
+            .count()
+    });
     println!("Part 2: {safe_count} in {}μs", time.as_micros());
+
+    if args.flag("explain") {
+        for (i, levels) in lines.iter().enumerate() {
+            let explanation = match rules.culprits(levels, tolerance) {
+                Some(culprits) if culprits.is_empty() => continue,
+                Some(culprits) => {
+                    let culprits = culprits
+                        .iter()
+                        .map(|x| format!("level {} ({})", x + 1, levels[*x]))
+                        .collect::<Vec<_>>();
+                    format!("safe without {}", culprits.join(", "))
+                }
+                None => "unsafe".into(),
+            };
+            println!("Report {}: {explanation}", i + 1);
+        }
+    }
 }
 
 // Time to parse: 289μs
@@ -70,7 +277,16 @@ fn main() {
 
 #[cfg(test)]
 mod tests {
-    use crate::{is_safe, is_safe_dampened};
+    use crate::{Direction, Rules};
+
+    const EXAMPLE: [[i32; 5]; 6] = [
+        [7, 6, 4, 2, 1],
+        [1, 2, 7, 8, 9],
+        [9, 7, 6, 2, 1],
+        [1, 3, 2, 4, 5],
+        [8, 6, 4, 4, 1],
+        [1, 3, 6, 7, 9],
+    ];
 
     #[test]
     fn part1() {
@@ -84,11 +300,18 @@ mod tests {
         ];
 
         (0..x.len()).for_each(|i| {
-            let state = if is_safe(&x[i]) { "Safe" } else { "Unsafe" };
+            let state = if Rules::default().is_safe(&x[i]) {
+                "Safe"
+            } else {
+                "Unsafe"
+            };
             println!("{i}: {state}");
         });
 
-        let count = x.into_iter().filter(|x| is_safe(x)).count();
+        let count = x
+            .into_iter()
+            .filter(|x| Rules::default().is_safe(x))
+            .count();
 
         assert_eq!(count, 2);
     }
@@ -104,7 +327,72 @@ mod tests {
             [1, 3, 6, 7, 9],
         ];
 
-        let count = x.into_iter().filter(|x| is_safe_dampened(x)).count();
+        let count = x
+            .into_iter()
+            .filter(|x| Rules::default().tolerates(x, 1))
+            .count();
         assert_eq!(count, 4);
     }
+
+    #[test]
+    fn culprits() {
+        let rules = Rules::default();
+        let culprits = EXAMPLE.map(|x| rules.culprits(&x, 1));
+        assert_eq!(
+            culprits,
+            [
+                Some(vec![]),
+                None,
+                None,
+                Some(vec![1]),
+                Some(vec![2]),
+                Some(vec![])
+            ]
+        );
+
+        assert_eq!(rules.culprits(&[1, 2, 7, 8, 9], 2), Some(vec![0, 1]));
+        assert_eq!(rules.culprits(&[1, 2, 7, 8, 3, 4], 2), Some(vec![2, 3]));
+        assert_eq!(rules.culprits(&[9, 1, 2, 3], 1), Some(vec![0]));
+        assert_eq!(rules.culprits(&[1, 2, 3, 9], 1), Some(vec![3]));
+        assert_eq!(rules.culprits(&[], 0), Some(vec![]));
+
+        let rules = Rules {
+            min_step: 0,
+            max_step: 5,
+            direction: Direction::Any,
+        };
+        assert!(rules.is_safe(&[1, 2, 7, 8, 8, 3]));
+
+        let rules = Rules {
+            direction: Direction::Decreasing,
+            ..Rules::default()
+        };
+        assert!(!rules.is_safe(&[1, 3, 6, 7, 9]));
+        assert_eq!(rules.culprits(&[5, 4, 6, 3], 1), Some(vec![2]));
+    }
+
+    #[test]
+    fn matches_brute_force() {
+        let mut rng = common::Rng::new(2);
+        let rules = Rules::default();
+
+        for _ in 0..500 {
+            let levels = (0..rng.range(1..8))
+                .map(|_| rng.range(0..12) as i32)
+                .collect::<Vec<i32>>();
+            let brute_force = (0..levels.len()).any(|i| {
+                let mut levels = levels.clone();
+                levels.remove(i);
+                rules.is_safe(&levels)
+            });
+
+            let culprits = rules.culprits(&levels, 1);
+            assert_eq!(rules.tolerates(&levels, 1), culprits.is_some());
+            assert_eq!(
+                culprits.is_some(),
+                rules.is_safe(&levels) || brute_force,
+                "{levels:?}"
+            );
+        }
+    }
 }
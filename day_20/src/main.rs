@@ -1,5 +1,8 @@
-use common::{timed, Grid, Pos};
-use pathfinding::directed::dijkstra::dijkstra;
+use common::{
+    params::Schema,
+    queue::{Queue, QueueKind},
+    search, timed, Args, Grid, Pos, Rng,
+};
 use std::{collections::HashMap, fmt::Display};
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -61,19 +64,13 @@ impl Map {
         }
     }
 
-    fn shortest_path(&self) -> Vec<Pos> {
-        dijkstra(
-            &self.start,
-            |pos| {
-                self.grid
-                    .iter_adjacent_cardinal(*pos)
-                    .filter(|(b, _)| matches!(b, Block::Empty | Block::End))
-                    .map(|(_, p)| (p, 1))
-            },
-            |pos| *pos == self.end,
-        )
-        .unwrap()
-        .0
+    fn shortest_path(&self, queue: QueueKind) -> Vec<Pos> {
+        let queue = Queue::new(queue, (self.grid.width * self.grid.height) as usize, 1);
+        let paths = search::grid_dijkstra(&self.grid, queue, self.start, Some(self.end), |b| {
+            matches!(b, Block::Empty | Block::End).then_some(1)
+        });
+
+        paths.path_to(&self.end).unwrap()
     }
 
     #[allow(dead_code)]
@@ -136,15 +133,63 @@ fn shortcuts(path_indices: &HashMap<Pos, usize>, path_pos: Pos, threshold: usize
     saved as u64
 }
 
+fn schema() -> Schema {
+    Schema::new()
+        .bounded("saving", 100u64, 1.., "picoseconds a cheat must save")
+        .bounded("cheat", 20u64, 2.., "longest cheat for part 2")
+        .param("queue", QueueKind::Binary, "binary, indexed or bucket")
+}
+
+fn generate(_rng: &mut Rng, args: &Args) -> String {
+    // A single serpentine track, one wall thick between rows so there are cheats
+    let size = args.get_or("size", 141usize) | 1;
+
+    let mut grid = vec![vec!['#'; size]; size];
+    let rows = (1..size - 1).step_by(2).collect::<Vec<_>>();
+
+    for (i, &y) in rows.iter().enumerate() {
+        grid[y][1..size - 1].fill('.');
+
+        if let Some(&next) = rows.get(i + 1) {
+            let x = if i.is_multiple_of(2) { size - 2 } else { 1 };
+            grid[(y + next) / 2][x] = '.';
+        }
+    }
+
+    let last = *rows.last().unwrap();
+    let end_x = if rows.len().is_multiple_of(2) {
+        1
+    } else {
+        size - 2
+    };
+    grid[1][1] = 'S';
+    grid[last][end_x] = 'E';
+
+    let grid = grid
+        .into_iter()
+        .map(|row| row.into_iter().collect::<String>())
+        .collect::<Vec<_>>();
+    grid.join("\n") + "\n"
+}
+
 fn main() {
+    let args = Args::from_env();
+    if args.run_generate(generate) {
+        return;
+    }
+
+    let params = args.params(&schema());
+    let saving: u64 = params.get("saving");
+    let cheat = params.get("cheat");
+
     let map = Map::new(&common::read_stdin());
-    let shortest_path = map.shortest_path();
+    let shortest_path = map.shortest_path(params.get("queue"));
     let path_indices = get_path_indices(&shortest_path);
 
     let (time, cheats) = timed(|| {
         shortest_path
             .iter()
-            .map(|p| shortcuts(&path_indices, *p, 100))
+            .map(|p| shortcuts(&path_indices, *p, saving as usize))
             .sum::<u64>()
     });
     println!("Part 1: {cheats} in {}ms", time.as_millis());
@@ -152,7 +197,7 @@ fn main() {
     let (time, long_cheats) = timed(|| {
         shortest_path
             .iter()
-            .map(|p| map.find_long_shortcuts(&path_indices, *p, 20, 100))
+            .map(|p| map.find_long_shortcuts(&path_indices, *p, cheat, saving))
             .sum::<u64>()
     });
     println!("Part 2: {long_cheats} in {}ms", time.as_millis());
@@ -164,6 +209,39 @@ fn main() {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use common::{differential::shrink_int, Differential};
+
+    #[test]
+    fn differential_test() {
+        fn track(size: u64) -> (Map, Vec<Pos>, HashMap<Pos, usize>) {
+            let args = Args::parse(["--size".to_string(), size.to_string()]);
+            let map = Map::new(&generate(&mut Rng::new(0), &args));
+            let path = map.shortest_path(QueueKind::Binary);
+            let path_indices = get_path_indices(&path);
+
+            (map, path, path_indices)
+        }
+
+        let d = Differential::new()
+            .register("shortcuts", |size: &u64| {
+                let (_, path, path_indices) = track(*size);
+                path.iter()
+                    .map(|p| shortcuts(&path_indices, *p, 2))
+                    .sum::<u64>()
+            })
+            .register("long shortcuts", |size: &u64| {
+                let (map, path, path_indices) = track(*size);
+                path.iter()
+                    .map(|p| map.find_long_shortcuts(&path_indices, *p, 2, 2))
+                    .sum::<u64>()
+            });
+
+        let shrink = |size: &u64| shrink_int(*size).into_iter().filter(|x| *x >= 5).collect();
+        let result = d.check_all((5..40).step_by(2), shrink);
+        if let Err(e) = result {
+            panic!("{e}");
+        }
+    }
 
     #[test]
     fn test() {
@@ -187,7 +265,7 @@ mod tests {
         ",
         );
 
-        let path = map.shortest_path();
+        let path = map.shortest_path(QueueKind::Binary);
         map.print_path(&path);
         assert_eq!(path.len() - 1, 84);
 
@@ -1,6 +1,6 @@
 #![allow(clippy::comparison_chain)]
 
-use common::{timed, Pos};
+use common::{params::Schema, timed, Args, Pos, Rng};
 use std::{
     collections::{HashMap, VecDeque},
     fmt::Display,
@@ -319,17 +319,39 @@ impl Display for Move {
     }
 }
 
+fn schema() -> Schema {
+    Schema::new()
+        .bounded("depth-1", 3u8, 1.., "keypads in the chain for part 1")
+        .bounded("depth-2", 26u8, 1.., "keypads in the chain for part 2")
+}
+
+fn generate(rng: &mut Rng, args: &Args) -> String {
+    let codes = args.get_or("codes", 5);
+
+    (0..codes)
+        .map(|_| format!("{:03}A\n", rng.below(1000)))
+        .collect()
+}
+
 fn main() {
+    let args = Args::from_env();
+    if args.run_generate(generate) {
+        return;
+    }
+
+    let params = args.params(&schema());
+    let (depth_1, depth_2) = (params.get("depth-1"), params.get("depth-2"));
+
     let input = common::read_stdin()
         .lines()
         .map(|x| x.to_string())
         .collect::<Vec<_>>();
     let (time, complexity): (_, u64) =
-        timed(|| input.iter().map(|x| Keypad::complexity(x, 3)).sum());
+        timed(|| input.iter().map(|x| Keypad::complexity(x, depth_1)).sum());
     println!("Part 1: {complexity} in {}μs", time.as_micros());
 
     let (time, complexity): (_, u64) =
-        timed(|| input.iter().map(|x| Keypad::complexity(x, 26)).sum());
+        timed(|| input.iter().map(|x| Keypad::complexity(x, depth_2)).sum());
     println!("Part 2: {complexity} in {}μs", time.as_micros());
 }
 
@@ -1,4 +1,4 @@
-use common::timed;
+use common::{params::Schema, timed, Args, Input, Rng};
 use std::collections::{HashMap, HashSet};
 
 fn mix(secret: i64, value: i64) -> i64 {
@@ -13,140 +13,229 @@ fn mix_prune(secret: i64, value: i64) -> i64 {
     prune(mix(secret, value))
 }
 
-#[derive(Debug, Default)]
-struct NumberGenerator(HashMap<i64, i64>);
-impl NumberGenerator {
-    fn new() -> Self {
-        Self::default()
-    }
+fn next_secret(number: i64) -> i64 {
+    let a = mix_prune(number, number * 64);
+    let b = mix_prune(a, a / 32);
+    mix_prune(b, b * 2048)
+}
 
-    fn generate(&mut self, number: i64) -> i64 {
-        if let Some(cached) = self.0.get(&number) {
-            *cached
-        } else {
-            let a = mix_prune(number, number * 64);
-            let b = mix_prune(a, a / 32);
-            let c = mix_prune(b, b * 2048);
+fn price(number: i64) -> i64 {
+    number % 10
+}
 
-            self.0.insert(number, c);
-            c
+/// Accumulates both answers one buyer at a time, so memory stays bounded
+/// by the number of distinct change sequences rather than the input size
+#[derive(Debug, Default)]
+struct Market {
+    secret_sum: i64,
+    sequence_values: HashMap<[i64; 4], i64>,
+}
+impl Market {
+    fn add_buyer(&mut self, secret: i64, n: usize) {
+        let mut visited = HashSet::new();
+        let mut changes = [0; 4];
+        let mut number = secret;
+
+        for i in 0..n {
+            let next = next_secret(number);
+            changes = [
+                changes[1],
+                changes[2],
+                changes[3],
+                price(next) - price(number),
+            ];
+            number = next;
+
+            if i >= 3 && visited.insert(changes) {
+                *self.sequence_values.entry(changes).or_default() += price(number);
+            }
         }
-    }
 
-    fn generate_n(&mut self, starting_number: i64, n: usize) -> i64 {
-        (0..n).fold(starting_number, |acc, _| self.generate(acc))
+        self.secret_sum += number;
     }
 
-    fn generate_n_iter(
-        &mut self,
-        starting_number: i64,
-        n: usize,
-    ) -> impl Iterator<Item = i64> + use<'_> {
-        (0..n).scan(starting_number, |acc, _| {
-            let val = self.generate(*acc);
-            *acc = val;
-            Some(val)
-        })
+    fn most_bananas(&self) -> i64 {
+        self.sequence_values.values().copied().max().unwrap_or(0)
     }
 }
 
-fn parse_input(input: &str) -> Vec<i64> {
-    input.lines().map(|l| l.parse().unwrap()).collect()
+fn schema() -> Schema {
+    Schema::new().bounded("secrets", 2000usize, 1.., "secrets generated per buyer")
 }
 
-fn price(number: i64) -> i64 {
-    number % 10
-}
+fn generate(rng: &mut Rng, args: &Args) -> String {
+    let buyers = args.get_or("buyers", 2000);
 
-fn get_numbers(input: i64, rng: &mut NumberGenerator) -> Vec<i64> {
-    rng.generate_n_iter(input, 2000).collect::<Vec<_>>()
+    (0..buyers)
+        .map(|_| format!("{}\n", rng.range(1..16777216)))
+        .collect()
 }
 
-fn get_changes(numbers: &[i64]) -> Vec<i64> {
-    let mut changes = vec![0];
-    for i in 1..numbers.len() {
-        let change = price(numbers[i]) - price(numbers[i - 1]);
-        changes.push(change);
+fn main() {
+    let args = Args::from_env();
+    if args.run_generate(generate) {
+        return;
     }
-    changes
-}
 
-fn find_first_occurence(changes: &[i64], sequence: &[i64; 4]) -> Option<usize> {
-    (3..changes.len()).find(|&i| {
-        changes[i] == sequence[3]
-            && changes[i - 1] == sequence[2]
-            && changes[i - 2] == sequence[1]
-            && changes[i - 3] == sequence[0]
-    })
+    let secrets = args.params(&schema()).get("secrets");
+
+    // One pass over the buyers gathers the sequence totals for part 2 too
+    let (time, market) = timed(|| {
+        let mut market = Market::default();
+        for line in Input::stdin().lines() {
+            market.add_buyer(line.parse().unwrap(), secrets);
+        }
+        market
+    });
+    println!("Part 1: {} in {}μs", market.secret_sum, time.as_micros());
+
+    let (time, bananas) = timed(|| market.most_bananas());
+    println!("Part 2: {bananas} in {}μs", time.as_micros());
 }
 
-fn find_best_sequence(numbers: &[Vec<i64>], changes: &[Vec<i64>]) -> ([i64; 4], i64) {
-    let mut seq_values = HashMap::new();
+// Part 1: 15335183969 in 797646μs
+// Part 2: 1696 in 204μs
 
-    for monkey in 0..numbers.len() {
-        let changes = &changes[monkey];
-        let numbers = &numbers[monkey];
-        let mut visited = HashSet::new();
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use common::{differential::shrink_vec, Differential};
+
+    // The original in-memory solution, as an oracle for `Market`
+    #[derive(Debug, Default)]
+    struct NumberGenerator(HashMap<i64, i64>);
+    impl NumberGenerator {
+        fn new() -> Self {
+            Self::default()
+        }
 
-        #[allow(clippy::needless_range_loop)]
-        for i in 3..changes.len() {
-            let seq = [changes[i - 3], changes[i - 2], changes[i - 1], changes[i]];
-            if visited.contains(&seq) {
-                continue;
+        fn generate(&mut self, number: i64) -> i64 {
+            if let Some(cached) = self.0.get(&number) {
+                *cached
+            } else {
+                let c = next_secret(number);
+
+                self.0.insert(number, c);
+                c
             }
+        }
 
-            let price = price(numbers[i]);
-            seq_values
-                .entry(seq)
-                .and_modify(|value| {
-                    *value += price;
-                })
-                .or_insert(price);
+        fn generate_n(&mut self, starting_number: i64, n: usize) -> i64 {
+            (0..n).fold(starting_number, |acc, _| self.generate(acc))
+        }
 
-            visited.insert(seq);
+        fn generate_n_iter(
+            &mut self,
+            starting_number: i64,
+            n: usize,
+        ) -> impl Iterator<Item = i64> + use<'_> {
+            (0..n).scan(starting_number, |acc, _| {
+                let val = self.generate(*acc);
+                *acc = val;
+                Some(val)
+            })
         }
     }
 
-    seq_values
-        .into_iter()
-        .max_by_key(|(_, price)| *price)
-        .unwrap()
-}
+    fn parse_input(input: &str) -> Vec<i64> {
+        input.lines().map(|l| l.parse().unwrap()).collect()
+    }
 
-fn get_most_bananas(input: &[i64], rng: &mut NumberGenerator) -> i64 {
-    let numbers = input
-        .iter()
-        .map(|x| get_numbers(*x, rng))
-        .collect::<Vec<_>>();
-    let changes = numbers.iter().map(|n| get_changes(n)).collect::<Vec<_>>();
-    let (sequence, _) = find_best_sequence(&numbers, &changes);
-    numbers
-        .iter()
-        .zip(changes.iter())
-        .filter_map(|(n, c)| {
-            let idx = find_first_occurence(c, &sequence);
-            idx.map(|i| price(n[i]))
-        })
-        .sum::<i64>()
-}
+    fn get_numbers(input: i64, rng: &mut NumberGenerator) -> Vec<i64> {
+        rng.generate_n_iter(input, 2000).collect::<Vec<_>>()
+    }
 
-fn main() {
-    let input = parse_input(&common::read_stdin());
-    let mut rng = NumberGenerator::new();
-    let (time, sum) = timed(|| input.iter().map(|x| rng.generate_n(*x, 2000)).sum::<i64>());
+    fn get_changes(numbers: &[i64]) -> Vec<i64> {
+        let mut changes = vec![0];
+        for i in 1..numbers.len() {
+            let change = price(numbers[i]) - price(numbers[i - 1]);
+            changes.push(change);
+        }
+        changes
+    }
 
-    println!("Part 1: {sum} in {}ms", time.as_millis());
+    fn find_first_occurence(changes: &[i64], sequence: &[i64; 4]) -> Option<usize> {
+        (3..changes.len()).find(|&i| {
+            changes[i] == sequence[3]
+                && changes[i - 1] == sequence[2]
+                && changes[i - 2] == sequence[1]
+                && changes[i - 3] == sequence[0]
+        })
+    }
 
-    let (time, bananas) = timed(|| get_most_bananas(&input, &mut rng));
-    println!("Part 2: {bananas} in {}ms", time.as_millis());
-}
+    fn find_best_sequence(numbers: &[Vec<i64>], changes: &[Vec<i64>]) -> ([i64; 4], i64) {
+        let mut seq_values = HashMap::new();
+
+        for monkey in 0..numbers.len() {
+            let changes = &changes[monkey];
+            let numbers = &numbers[monkey];
+            let mut visited = HashSet::new();
+
+            #[allow(clippy::needless_range_loop)]
+            for i in 3..changes.len() {
+                let seq = [changes[i - 3], changes[i - 2], changes[i - 1], changes[i]];
+                if visited.contains(&seq) {
+                    continue;
+                }
+
+                let price = price(numbers[i]);
+                seq_values
+                    .entry(seq)
+                    .and_modify(|value| {
+                        *value += price;
+                    })
+                    .or_insert(price);
+
+                visited.insert(seq);
+            }
+        }
 
-// Part 1: 15335183969 in 801ms
-// Part 2: 1696 in 2447ms
+        seq_values
+            .into_iter()
+            .max_by_key(|(_, price)| *price)
+            .unwrap()
+    }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+    fn get_most_bananas(input: &[i64], rng: &mut NumberGenerator) -> i64 {
+        let numbers = input
+            .iter()
+            .map(|x| get_numbers(*x, rng))
+            .collect::<Vec<_>>();
+        let changes = numbers.iter().map(|n| get_changes(n)).collect::<Vec<_>>();
+        let (sequence, _) = find_best_sequence(&numbers, &changes);
+        numbers
+            .iter()
+            .zip(changes.iter())
+            .filter_map(|(n, c)| {
+                let idx = find_first_occurence(c, &sequence);
+                idx.map(|i| price(n[i]))
+            })
+            .sum::<i64>()
+    }
+
+    #[test]
+    fn differential_test() {
+        let d = Differential::new()
+            .register("in memory", |buyers: &Vec<i64>| {
+                let mut rng = NumberGenerator::new();
+                let sum = buyers.iter().map(|x| rng.generate_n(*x, 2000)).sum::<i64>();
+                (sum, get_most_bananas(buyers, &mut rng))
+            })
+            .register("streaming", |buyers: &Vec<i64>| {
+                let mut market = Market::default();
+                for x in buyers {
+                    market.add_buyer(*x, 2000);
+                }
+                (market.secret_sum, market.most_bananas())
+            });
+
+        let args = Args::parse(["--buyers", "10"]);
+        let generate = |rng: &mut Rng| parse_input(&generate(rng, &args));
+        let result = d.check_generated(0..5, generate, |x| shrink_vec(x));
+        if let Err(e) = result {
+            panic!("{e}");
+        }
+    }
 
     #[test]
     fn generate_test() {
@@ -214,6 +303,21 @@ mod tests {
         assert_eq!(get_most_bananas(&[1, 2, 3, 2024], &mut rng), 23);
     }
 
+    #[test]
+    fn market_test() {
+        let mut market = Market::default();
+        for x in [1, 10, 100, 2024] {
+            market.add_buyer(x, 2000);
+        }
+        assert_eq!(market.secret_sum, 37327623);
+
+        let mut market = Market::default();
+        for x in [1, 2, 3, 2024] {
+            market.add_buyer(x, 2000);
+        }
+        assert_eq!(market.most_bananas(), 23);
+    }
+
     #[test]
     fn changes_test() {
         let numbers = [
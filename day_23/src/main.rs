@@ -1,8 +1,9 @@
-use common::{iter::UniqueIter, timed};
-use std::{
-    collections::{HashMap, HashSet},
-    fmt::Debug,
+use common::{
+    graph::{Graph, NodeId},
+    intern::Name2,
+    timed, Args, Rng, Tap,
 };
+use std::fmt::Debug;
 
 fn parse_input(input: &str) -> impl Iterator<Item = (&str, &str)> {
     input
@@ -13,111 +14,82 @@ fn parse_input(input: &str) -> impl Iterator<Item = (&str, &str)> {
 
 #[derive(Debug)]
 struct Connections {
-    connections: HashMap<String, HashSet<String>>,
+    graph: Graph<Name2>,
 }
 impl Connections {
-    fn new<S>(connections: impl IntoIterator<Item = (S, S)>) -> Self
-    where
-        S: Into<String>,
-    {
-        use std::collections::hash_map::Entry;
-
-        let mut map = HashMap::<String, HashSet<String>>::new();
+    fn new<'a>(connections: impl IntoIterator<Item = (&'a str, &'a str)>) -> Self {
+        let mut graph = Graph::undirected();
         for (a, b) in connections {
-            let a: String = a.into();
-            let b: String = b.into();
-
-            match map.entry(a.clone()) {
-                Entry::Occupied(mut e) => {
-                    e.get_mut().insert(b.clone());
-                }
-                Entry::Vacant(e) => {
-                    e.insert([b.clone()].into());
-                }
-            }
-
-            match map.entry(b) {
-                Entry::Occupied(mut e) => {
-                    e.get_mut().insert(a);
-                }
-                Entry::Vacant(e) => {
-                    e.insert([a].into());
-                }
-            }
+            graph.add_edge(name(a), name(b));
         }
 
-        Self { connections: map }
+        Self { graph }
     }
 
-    fn get_connected(&self, computer: &str) -> Option<Vec<[String; 3]>> {
-        let connections = self.connections.get(computer)?;
-        let mut groups = vec![];
-        let mut seen = HashSet::<_>::new();
-        for a in connections {
-            let a_conn = self.connections.get(a)?;
-
-            for b in connections {
-                if a == b {
-                    continue;
-                }
-
-                let sorted = if a < b { (a, b) } else { (b, a) };
-
-                if a_conn.contains(b) && !seen.contains(&sorted) {
-                    seen.insert(sorted);
-                    groups.push([computer.into(), a.clone(), b.clone()]);
-                }
-            }
-        }
-
-        Some(groups)
+    fn names(&self, ids: &[NodeId]) -> Vec<Name2> {
+        ids.iter()
+            .map(|id| *self.graph.node(*id))
+            .collect::<Vec<_>>()
+            .tap_mut(|x| x.sort())
     }
 
-    fn sets(&self) -> Vec<[String; 3]> {
-        self.connections
-            .keys()
-            .filter_map(|pc| self.get_connected(pc))
-            .flatten()
-            .map(|mut x| {
-                x.sort();
-                x
-            })
-            .unique()
+    fn sets(&self) -> Vec<[Name2; 3]> {
+        self.graph
+            .triangles()
+            .into_iter()
+            .map(|set| self.names(&set).try_into().unwrap())
             .collect()
     }
+}
 
-    fn get_all_connected(&self, computer: &str) -> HashSet<String> {
-        let conn = &self.connections[computer];
-
-        let x = conn.iter().flat_map(|c| {
-            self.connections[c].iter().filter(|c2| {
-                if *c2 == computer {
-                    true
-                } else {
-                    conn.contains(*c2)
-                }
-            })
-        });
+fn name(s: &str) -> Name2 {
+    s.parse()
+        .unwrap_or_else(|e| panic!("Invalid computer name: {e}"))
+}
 
-        x.unique().cloned().collect()
+fn generate(rng: &mut Rng, args: &Args) -> String {
+    let nodes = args.get_or("nodes", 520usize).min(26 * 26);
+    let degree = args.get_or("degree", 13usize);
+    let clique = args.get_or("clique", 13usize).min(nodes);
+
+    let mut names = ('a'..='z')
+        .flat_map(|a| ('a'..='z').map(move |b| format!("{a}{b}")))
+        .collect::<Vec<_>>();
+    rng.shuffle(&mut names);
+    names.truncate(nodes);
+
+    let mut edges = std::collections::BTreeSet::new();
+    for a in 0..clique {
+        for b in a + 1..clique {
+            edges.insert((a, b));
+        }
     }
-
-    fn get_all_inter_connected(&self, computer: &str) -> HashSet<String> {
-        let mut connected = self.get_all_connected(computer);
-        let others = connected.iter().cloned().collect::<Vec<_>>();
-
-        for c in others {
-            connected = connected
-                .intersection(&self.get_all_connected(&c))
-                .cloned()
-                .collect();
+    for a in 0..nodes {
+        for _ in 0..degree / 2 {
+            let b = rng.index(nodes);
+            if a != b {
+                edges.insert((a.min(b), a.max(b)));
+            }
         }
-
-        connected
     }
+
+    let mut edges = edges
+        .into_iter()
+        .map(|(a, b)| match rng.chance(0.5) {
+            true => format!("{}-{}\n", names[a], names[b]),
+            false => format!("{}-{}\n", names[b], names[a]),
+        })
+        .collect::<Vec<_>>();
+    rng.shuffle(&mut edges);
+    edges.concat()
 }
 
 fn main() {
+    let args = Args::from_env();
+    if args.run_generate(generate) {
+        return;
+    }
+
     let input = common::read_stdin();
     let input = parse_input(&input);
 
@@ -141,17 +113,10 @@ fn main() {
 // Part 2: av,fr,gj,hk,ii,je,jo,lq,ny,qd,uq,wq,xc in 222ms
 
 fn get_most_connected(c: &Connections) -> Vec<String> {
-    c.connections
-        .keys()
-        .map(|pc| c.get_all_inter_connected(pc))
-        .map(|x| x.into_iter().collect::<Vec<_>>())
-        .map(|mut x| {
-            x.sort();
-            x
-        })
-        .unique()
-        .max_by_key(|v| v.len())
-        .unwrap()
+    c.names(&c.graph.maximum_clique())
+        .iter()
+        .map(|x| x.to_string())
+        .collect()
 }
 
 #[cfg(test)]
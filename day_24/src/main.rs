@@ -1,4 +1,8 @@
-use common::{timed, Tap};
+use common::{
+    graph::Graph,
+    intern::{Interner, Symbol},
+    parse, timed, Args, Rng, Tap,
+};
 use std::collections::{HashMap, HashSet};
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -19,117 +23,164 @@ impl Gate {
 
 #[derive(Debug, Clone)]
 struct Command {
-    a: String,
-    b: String,
+    a: Symbol,
+    b: Symbol,
     gate: Gate,
-    output: String,
+    output: Symbol,
 }
 
 #[derive(Debug, Clone)]
 struct Device {
-    inputs: HashMap<String, u8>,
+    names: Interner,
+    inputs: HashMap<Symbol, u8>,
     commands: Vec<Command>,
+    /// Edges from each gate input wire to its output wire
+    wiring: Graph<Symbol>,
+    /// Index of the command driving each wire
+    by_output: HashMap<Symbol, usize>,
 }
 impl Device {
     fn new(input: &str) -> Self {
-        let (inputs, commands) = input.split_once("\n\n").unwrap();
-
-        let inputs = inputs
-            .lines()
-            .filter_map(|line| {
-                let (variable, value) = line.trim_ascii().split_once(": ")?;
-                Some((variable.into(), value.parse().unwrap()))
-            })
+        let [inputs, commands] = parse::sections(input)[..] else {
+            panic!("Expected wire values and gates separated by a blank line");
+        };
+
+        let mut names = Interner::new();
+
+        let inputs = parse::lines_with(inputs, |p| {
+            let variable = names.intern(p.word()?);
+            p.tag(":")?;
+            p.spaces();
+            Ok((variable, p.int()?))
+        })
+        .unwrap_or_else(|e| panic!("Invalid wire value: {e}"))
+        .into_iter()
+        .collect();
+
+        let commands = parse::lines_with(commands, |p| {
+            let a = names.intern(p.word()?);
+            p.spaces();
+            let gate = if p.try_tag("AND") {
+                Gate::And
+            } else if p.try_tag("XOR") {
+                Gate::Xor
+            } else if p.try_tag("OR") {
+                Gate::Or
+            } else {
+                return Err(p.error("AND, OR or XOR"));
+            };
+            p.spaces();
+            let b = names.intern(p.word()?);
+            p.spaces();
+            p.tag("->")?;
+            p.spaces();
+            let output = names.intern(p.word()?);
+
+            Ok(Command { a, b, gate, output })
+        })
+        .unwrap_or_else(|e| panic!("Invalid gate: {e}"));
+
+        let mut wiring = Graph::directed();
+        for c in &commands {
+            wiring.add_edge(c.a, c.output);
+            wiring.add_edge(c.b, c.output);
+        }
+
+        let by_output = commands
+            .iter()
+            .enumerate()
+            .map(|(i, c)| (c.output, i))
             .collect();
 
-        let commands = commands
-            .lines()
-            .filter_map(|line| {
-                let mut line = line.trim_ascii().split_ascii_whitespace();
-                let a = line.next()?;
-                let gate = line.next()?;
-                let b = line.next()?;
-                let _ = line.next()?;
-                let output = line.next()?;
-
-                Some(Command {
-                    a: a.into(),
-                    b: b.into(),
-                    output: output.into(),
-                    gate: match gate {
-                        "AND" => Gate::And,
-                        "OR" => Gate::Or,
-                        "XOR" => Gate::Xor,
-                        x => panic!("Unexpected gate: {x}"),
-                    },
-                })
-            })
-            .collect::<Vec<_>>();
+        Device {
+            names,
+            inputs,
+            commands,
+            wiring,
+            by_output,
+        }
+    }
 
-        Device { inputs, commands }
+    /// Commands reading `wire`
+    fn consumers(&self, wire: Symbol) -> impl Iterator<Item = &Command> {
+        let id = self.wiring.id(&wire);
+        id.into_iter()
+            .flat_map(|id| self.wiring.neighbors(id))
+            .map(|out| &self.commands[self.by_output[self.wiring.node(out)]])
     }
 
-    fn run(mut self) -> Vec<(String, u8)> {
-        let mut visited = HashSet::new();
-
-        while visited.len() < self.commands.len() {
-            for (i, c) in self.commands.iter().enumerate() {
-                if visited.contains(&i) {
-                    continue;
-                } else if self.inputs.contains_key(&c.a) && self.inputs.contains_key(&c.b) {
-                    visited.insert(i);
-
-                    let a = self.inputs[&c.a];
-                    let b = self.inputs[&c.b];
-                    let val = c.gate.apply(a, b);
-
-                    self.inputs
-                        .entry(c.output.clone())
-                        .and_modify(|e| *e = val)
-                        .or_insert(val);
-                }
+    fn to_dot(&self) -> String {
+        self.wiring.to_dot_with(|_, symbol| {
+            let wire = &self.names[*symbol];
+            match self.by_output.get(symbol) {
+                Some(i) => format!("label=\"{wire}\\n{:?}\"", self.commands[*i].gate),
+                None => format!("label=\"{wire}\", shape=box"),
             }
+        })
+    }
+
+    fn run(mut self) -> Vec<(String, u8)> {
+        let order = self.wiring.toposort().unwrap_or_else(|cycle| {
+            let cycle = cycle
+                .iter()
+                .map(|id| &self.names[*self.wiring.node(*id)])
+                .collect::<Vec<_>>();
+            panic!("Wiring contains a loop: {cycle:?}")
+        });
+
+        for id in order {
+            let Some(i) = self.by_output.get(self.wiring.node(id)) else {
+                continue;
+            };
+            let c = &self.commands[*i];
+
+            let a = self.inputs[&c.a];
+            let b = self.inputs[&c.b];
+            let val = c.gate.apply(a, b);
+
+            self.inputs.insert(c.output, val);
         }
 
-        self.inputs.into_iter().collect()
+        self.inputs
+            .into_iter()
+            .map(|(wire, val)| (self.names[wire].to_string(), val))
+            .collect()
     }
 
     fn get_swaps(&self) -> Vec<String> {
+        let name = |wire: Symbol| &self.names[wire];
         let max_z = self
             .commands
             .iter()
-            .filter(|x| x.output.starts_with('z'))
-            .map(|x| &x.output)
+            .map(|x| name(x.output))
+            .filter(|x| x.starts_with('z'))
             .max()
             .unwrap();
         let mut incorrect = HashSet::new();
 
         for cmd in &self.commands {
+            let (a, b, output) = (name(cmd.a), name(cmd.b), name(cmd.output));
             #[allow(clippy::if_same_then_else)]
-            if cmd.output.starts_with('z') && cmd.gate != Gate::Xor && cmd.output != *max_z {
-                incorrect.insert(cmd.output.clone());
+            if output.starts_with('z') && cmd.gate != Gate::Xor && output != max_z {
+                incorrect.insert(output);
             } else if cmd.gate == Gate::Xor
-                && !cmd.a.starts_with(['x', 'y', 'z'])
-                && !cmd.b.starts_with(['x', 'y', 'z'])
-                && !cmd.output.starts_with(['x', 'y', 'z'])
+                && !a.starts_with(['x', 'y', 'z'])
+                && !b.starts_with(['x', 'y', 'z'])
+                && !output.starts_with(['x', 'y', 'z'])
             {
-                incorrect.insert(cmd.output.clone());
-            } else if cmd.gate == Gate::And && (cmd.a != "x00" && cmd.b != "x00") {
-                for c2 in &self.commands {
-                    if (cmd.output == c2.a || cmd.output == c2.b) && c2.gate != Gate::Or {
-                        incorrect.insert(cmd.output.clone());
-                    }
-                }
-            } else if cmd.gate == Gate::Xor {
-                for c2 in &self.commands {
-                    if (cmd.output == c2.a || cmd.output == c2.b) && c2.gate == Gate::Or {
-                        incorrect.insert(cmd.output.clone());
-                    }
+                incorrect.insert(output);
+            } else if cmd.gate == Gate::And && (a != "x00" && b != "x00") {
+                if self.consumers(cmd.output).any(|c2| c2.gate != Gate::Or) {
+                    incorrect.insert(output);
                 }
+            } else if cmd.gate == Gate::Xor
+                && self.consumers(cmd.output).any(|c2| c2.gate == Gate::Or)
+            {
+                incorrect.insert(output);
             }
         }
 
-        let mut incorrect = Vec::from_iter(incorrect);
+        let mut incorrect = incorrect.into_iter().map(String::from).collect::<Vec<_>>();
         incorrect.sort();
         incorrect
     }
@@ -150,8 +201,92 @@ fn combine(data: &[(String, u8)], prefix: char) -> u64 {
     })
 }
 
+fn generate(rng: &mut Rng, args: &Args) -> String {
+    let bits = args.get_or("bits", 45usize).max(2);
+    let swaps = args.get_or("swaps", 0);
+
+    let mut used = HashSet::new();
+    let mut wire = |rng: &mut Rng| loop {
+        let name = (0..3)
+            .map(|_| char::from(b'a' + rng.below(23) as u8))
+            .collect::<String>();
+        if used.insert(name.clone()) {
+            return name;
+        }
+    };
+
+    // Ripple-carry adder, one stage per bit
+    let mut gates = vec![("x00".into(), Gate::Xor, "y00".into(), "z00".into())];
+    let mut carry = wire(rng);
+    gates.push(("x00".into(), Gate::And, "y00".into(), carry.clone()));
+
+    let mut stages = vec![];
+    for i in 1..bits {
+        let (x, y, z) = (format!("x{i:02}"), format!("y{i:02}"), format!("z{i:02}"));
+        let [sum, and, partial] = [(); 3].map(|_| wire(rng));
+        let next = if i == bits - 1 {
+            format!("z{bits:02}")
+        } else {
+            wire(rng)
+        };
+
+        stages.push(gates.len());
+        gates.push((x.clone(), Gate::Xor, y.clone(), sum.clone()));
+        gates.push((x, Gate::And, y, and.clone()));
+        gates.push((sum.clone(), Gate::Xor, carry.clone(), z));
+        gates.push((sum, Gate::And, carry, partial.clone()));
+        gates.push((and, Gate::Or, partial, next.clone()));
+        carry = next;
+    }
+
+    // Only swap outputs within a stage in ways that can't create a cycle
+    rng.shuffle(&mut stages);
+    for &stage in stages.iter().take(swaps) {
+        let (a, b) = if rng.chance(0.5) {
+            (stage, stage + 1)
+        } else {
+            (stage + 2, stage + 4)
+        };
+        let tmp = gates[a].3.clone();
+        gates[a].3 = gates[b].3.clone();
+        gates[b].3 = tmp;
+    }
+    rng.shuffle(&mut gates);
+
+    let inputs = ['x', 'y']
+        .iter()
+        .flat_map(|c| (0..bits).map(move |i| format!("{c}{i:02}")))
+        .map(|name| format!("{name}: {}\n", rng.below(2)))
+        .collect::<String>();
+
+    let gates = gates
+        .into_iter()
+        .map(|(a, gate, b, out)| {
+            let (a, b) = if rng.chance(0.5) { (a, b) } else { (b, a) };
+            let gate = match gate {
+                Gate::And => "AND",
+                Gate::Or => "OR",
+                Gate::Xor => "XOR",
+            };
+            format!("{a} {gate} {b} -> {out}\n")
+        })
+        .collect::<String>();
+
+    inputs + "\n" + &gates
+}
+
 fn main() {
+    let args = Args::from_env();
+    if args.run_generate(generate) {
+        return;
+    }
+
     let device = Device::new(&common::read_stdin());
+    if args.flag("dot") {
+        print!("{}", device.to_dot());
+        return;
+    }
+
     let (time, result) = timed(|| combine(&device.clone().run(), 'z'));
     println!("Part 1: {result} in {}μs", time.as_micros());
 
@@ -170,12 +305,16 @@ mod tests {
     fn test() {
         let device = Device::new(include_str!("../example.txt"));
 
-        let initial = device.inputs.clone();
+        let initial = device
+            .inputs
+            .keys()
+            .map(|wire| device.names[*wire].to_string())
+            .collect::<HashSet<_>>();
         let mut result = device
             .clone()
             .run()
             .into_iter()
-            .filter(|x| !initial.contains_key(&x.0))
+            .filter(|x| !initial.contains(&x.0))
             .collect::<Vec<_>>();
         result.sort();
         assert_eq!(
@@ -223,6 +362,18 @@ mod tests {
         assert_eq!(combine(&result, 'z'), 2024);
     }
 
+    #[test]
+    fn generate_test() {
+        let args = Args::parse(["generate", "--bits", "16"]);
+        let device = Device::new(&generate(&mut Rng::new(1), &args));
+        let result = device.clone().run();
+
+        assert_eq!(
+            combine(&result, 'z'),
+            combine(&result, 'x') + combine(&result, 'y')
+        );
+    }
+
     #[test]
     fn swap_test() {
         let input = "
@@ -1,31 +1,35 @@
+use common::{bits, Args, Rng};
+
 #[derive(Debug, Default)]
 struct State {
     keys: Vec<[u8; 5]>,
     locks: Vec<[u8; 5]>,
+    /// Filled cells of each schematic, packed row by row
+    key_masks: Vec<u64>,
+    lock_masks: Vec<u64>,
 }
 impl State {
     fn new(input: &str) -> Self {
         fn add_key_or_lock(this: &mut State, input: &str) {
-            let pins = input
+            let rows = input
                 .lines()
-                .map(|line| line.chars().collect::<Vec<_>>())
+                .map(|line| bits::row_mask(line, '#'))
                 .collect::<Vec<_>>();
 
-            let mut body = [0; 5];
-
-            #[allow(clippy::needless_range_loop)]
-            for x in 0..5 {
-                for y in 1..=5 {
-                    if pins[y][x] == '#' {
-                        body[x] += 1;
-                    }
-                }
-            }
+            let mask = bits::pack_rows(&rows, 5).expect("schematic fits in 64 cells");
+            let body = bits::column_counts(&rows[1..rows.len() - 1], 5)
+                .iter()
+                .map(|x| *x as u8)
+                .collect::<Vec<_>>()
+                .try_into()
+                .unwrap();
 
-            if pins[0].iter().all(|c| *c == '#') {
+            if rows[0] == 0b11111 {
                 this.locks.push(body);
+                this.lock_masks.push(mask);
             } else {
-                this.keys.push(body)
+                this.keys.push(body);
+                this.key_masks.push(mask);
             }
         }
 
@@ -38,21 +42,12 @@ impl State {
     }
 
     fn find_matches(&self) -> Vec<([u8; 5], [u8; 5])> {
-        fn has_overlap(lock: &[u8; 5], key: &[u8; 5]) -> bool {
-            for i in 0..5 {
-                if lock[i] + key[i] > 5 {
-                    return false;
-                }
-            }
-
-            true
-        }
-
         let mut matches = vec![];
 
-        for lock in self.locks.iter() {
-            for key in self.keys.iter() {
-                if has_overlap(lock, key) {
+        for (lock, lock_mask) in self.locks.iter().zip(&self.lock_masks) {
+            for (key, key_mask) in self.keys.iter().zip(&self.key_masks) {
+                // A key fits when no cell is filled by both
+                if lock_mask & key_mask == 0 {
                     matches.push((*lock, *key));
                 }
             }
@@ -62,7 +57,39 @@ impl State {
     }
 }
 
+fn generate(rng: &mut Rng, args: &Args) -> String {
+    let count = args.get_or("count", 500);
+
+    let schematics = (0..count).map(|_| {
+        let is_lock = rng.chance(0.5);
+        let heights = [(); 5].map(|_| rng.below(6) as usize);
+
+        let rows = (0..7).map(|row| {
+            heights
+                .iter()
+                .map(|&h| {
+                    let filled = if is_lock { row <= h } else { row >= 6 - h };
+                    if filled {
+                        '#'
+                    } else {
+                        '.'
+                    }
+                })
+                .collect::<String>()
+        });
+
+        rows.collect::<Vec<_>>().join("\n")
+    });
+
+    schematics.collect::<Vec<_>>().join("\n\n") + "\n"
+}
+
 fn main() {
+    let args = Args::from_env();
+    if args.run_generate(generate) {
+        return;
+    }
+
     let state = State::new(&common::read_stdin());
 
     println!("Part 1: {}", state.find_matches().len());
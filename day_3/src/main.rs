@@ -1,156 +1,399 @@
-use common::timed;
+use common::{
+    parse::{ParseError, ParseResult, Parser},
+    timed, Args, Rng,
+};
 use std::{
+    collections::{HashMap, HashSet},
     io::Read,
-    iter::{from_fn, Peekable},
+    ops::Range,
 };
 
+#[derive(Debug, Clone, Copy)]
+enum Effect {
+    /// Adds the value to the result, `None` on overflow
+    Value(fn(&[i64]) -> Option<i64>),
+    /// Switches evaluation of later instructions on or off
+    Enable(bool),
+}
+
+/// `name(arg,...)` with `arity` unsigned arguments of 1 to `max_digits` digits
+#[derive(Debug, Clone, Copy)]
+struct Instruction {
+    name: &'static str,
+    arity: usize,
+    max_digits: usize,
+    effect: Effect,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Token {
+    /// Index into [`Scanner::instructions`]
+    instruction: usize,
+    args: Vec<i64>,
+    /// Bytes of the input this token was read from
+    span: Range<usize>,
+}
+
+/// Why [`Scanner::evaluate`] ignored an instruction
 #[derive(Debug, Clone, PartialEq, Eq)]
-struct Mul {
-    a: i32,
-    b: i32,
-    enabled: bool,
+enum Skip {
+    /// Disabled by the toggle at this span
+    Disabled(Range<usize>),
+    Overflow,
+}
+
+/// An instruction name followed by something that doesn't parse, like
+/// `mul[3,7]` or `mul(32,64]`
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct NearMiss {
+    /// Index into [`Scanner::instructions`]
+    instruction: usize,
+    /// From the name up to and including the offending character
+    span: Range<usize>,
+    /// Positioned relative to the start of the span
+    error: ParseError,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+struct Scan {
+    tokens: Vec<Token>,
+    near_misses: Vec<NearMiss>,
+}
+
+/// How [`Scanner::annotate`] marks a span
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Mark {
+    Enabled,
+    Skipped,
+    Toggle,
+    NearMiss,
 }
-impl Mul {
-    fn mul(&self) -> i32 {
-        self.a * self.b
+impl Mark {
+    fn colour(self) -> &'static str {
+        match self {
+            Self::Enabled => "\x1b[32m",
+            Self::Skipped => "\x1b[2;33m",
+            Self::Toggle => "\x1b[36m",
+            Self::NearMiss => "\x1b[4;31m",
+        }
+    }
+
+    /// Opening marker when colours are off, closed by `>`
+    fn plain(self) -> &'static str {
+        match self {
+            Self::Enabled => "<+",
+            Self::Skipped => "<-",
+            Self::Toggle => "<~",
+            Self::NearMiss => "<!",
+        }
     }
 }
 
-fn tokenize_part_1(input: &str) -> Option<Vec<Mul>> {
-    let mut chars = input.chars().peekable();
-    let mut ops = vec![];
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+struct Evaluation {
+    total: i64,
+    /// Span of each skipped instruction and why
+    skipped: Vec<(Range<usize>, Skip)>,
+}
+
+/// Finds registered instructions in corrupted memory, skipping everything else
+#[derive(Debug, Clone, Default)]
+struct Scanner {
+    /// Longest names first, so `don't` is tried before `do`
+    instructions: Vec<Instruction>,
+}
+impl Scanner {
+    fn new() -> Self {
+        Self::default()
+    }
+
+    fn register(mut self, instruction: Instruction) -> Self {
+        assert!(
+            self.get(instruction.name).is_none(),
+            "{} registered twice",
+            instruction.name
+        );
+
+        let i = self
+            .instructions
+            .partition_point(|x| x.name.len() >= instruction.name.len());
+        self.instructions.insert(i, instruction);
+        self
+    }
+
+    fn instruction(
+        self,
+        name: &'static str,
+        arity: usize,
+        max_digits: usize,
+        value: fn(&[i64]) -> Option<i64>,
+    ) -> Self {
+        self.register(Instruction {
+            name,
+            arity,
+            max_digits,
+            effect: Effect::Value(value),
+        })
+    }
+
+    /// `name()`, enabling or disabling later instructions
+    fn toggle(self, name: &'static str, enables: bool) -> Self {
+        self.register(Instruction {
+            name,
+            arity: 0,
+            max_digits: 0,
+            effect: Effect::Enable(enables),
+        })
+    }
 
-    'outer: while let Some(ch) = chars.next() {
-        if ch == 'm' {
-            if chars.next_if(|c| *c == 'u').is_none()
-                || chars.next_if(|c| *c == 'l').is_none()
-                || chars.next_if(|c| *c == '(').is_none()
-            {
+    fn part_1() -> Self {
+        Self::new().instruction("mul", 2, 3, |x| x[0].checked_mul(x[1]))
+    }
+
+    fn part_2() -> Self {
+        Self::part_1().toggle("do", true).toggle("don't", false)
+    }
+
+    fn get(&self, name: &str) -> Option<usize> {
+        self.instructions.iter().position(|x| x.name == name)
+    }
+
+    fn parse_args(&self, p: &mut Parser, instruction: &Instruction) -> ParseResult<Vec<i64>> {
+        p.tag(instruction.name)?;
+        p.tag("(")?;
+
+        let mut args = Vec::with_capacity(instruction.arity);
+        for i in 0..instruction.arity {
+            if i > 0 {
+                p.tag(",")?;
+            }
+
+            let start = *p;
+            let digits = p.take_while(|c| c.is_ascii_digit());
+            if !(1..=instruction.max_digits).contains(&digits.len()) {
+                return Err(start.error(format!("1 to {} digits", instruction.max_digits)));
+            }
+            let arg = digits
+                .parse()
+                .map_err(|_| start.error(format!("an argument up to {}", i64::MAX)))?;
+            args.push(arg);
+        }
+
+        p.tag(")")?;
+        Ok(args)
+    }
+
+    /// Instruction starting at the parser's position, trying every name that
+    /// matches there. `None` if no name matches, otherwise the first
+    /// instruction that parses or the error from the longest name. Errors are
+    /// positioned relative to the start of the instruction
+    fn next_token(&self, p: &mut Parser) -> Option<Result<Token, (usize, ParseError)>> {
+        let start = p.offset();
+        let mut error = None;
+
+        for (i, instruction) in self.instructions.iter().enumerate() {
+            if !p.rest().starts_with(instruction.name) {
                 continue;
             }
 
-            let mut numbers = vec![];
+            // A parser over just the rest keeps error positions cheap to find
+            let mut attempt = Parser::new(p.rest());
+            match self.parse_args(&mut attempt, instruction) {
+                Ok(args) => {
+                    p.take(attempt.offset());
+                    return Some(Ok(Token {
+                        instruction: i,
+                        args,
+                        span: start..p.offset(),
+                    }));
+                }
+                Err(e) => {
+                    error.get_or_insert((i, e));
+                }
+            }
+        }
 
-            loop {
-                match chars.peek() {
-                    Some('0'..='9') => {
-                        numbers.push(parse_number(&mut chars));
-                    }
-                    Some(',') => {
-                        chars.next();
-                        continue;
-                    }
-                    Some(')') => {
-                        chars.next();
-                        break;
+        error.map(Err)
+    }
+
+    /// Every well-formed instruction in `input` in order, and every near miss.
+    /// A name that continues as a longer word, like `do` in `do_not_`, isn't a
+    /// near miss
+    fn scan(&self, input: &str) -> Scan {
+        let mut p = Parser::new(input);
+        let mut scan = Scan::default();
+        let starts = self
+            .instructions
+            .iter()
+            .filter_map(|x| x.name.chars().next())
+            .collect::<Vec<_>>();
+
+        while !p.is_done() {
+            match self.next_token(&mut p) {
+                Some(Ok(token)) => scan.tokens.push(token),
+                Some(Err((instruction, error))) => {
+                    let start = p.offset();
+                    let rest = &input[start + self.instructions[instruction].name.len()..];
+                    if !rest.starts_with(|c: char| c.is_alphanumeric() || c == '_') {
+                        let offending = p.rest()[error.offset..].chars().next();
+                        let end = start + error.offset + offending.map_or(0, char::len_utf8);
+                        scan.near_misses.push(NearMiss {
+                            instruction,
+                            span: start..end,
+                            error,
+                        });
                     }
-                    Some(_x) => continue 'outer, //panic!("Unexpected character in mul: {x}"),
-                    None => continue 'outer,     //panic!("Unexpected end of stream in mul"),
+
+                    p.next_char();
+                    p.take_while(|c| !starts.contains(&c));
+                }
+                None => {
+                    p.next_char();
+                    p.take_while(|c| !starts.contains(&c));
                 }
             }
-
-            ops.push(Mul {
-                a: numbers[0],
-                b: numbers[1],
-                enabled: true,
-            });
         }
+
+        scan
     }
 
-    Some(ops)
-}
+    fn evaluate(&self, tokens: &[Token]) -> Evaluation {
+        let mut evaluation = Evaluation::default();
+        let mut disabled_by = None;
 
-fn tokenize_part_2(input: &str) -> Option<Vec<Mul>> {
-    let mut chars = input.chars().peekable();
-    let mut ops = vec![];
-    let mut enabled = true;
-
-    'outer: while let Some(ch) = chars.next() {
-        match ch {
-            'm' => {
-                if chars.next_if(|c| *c == 'u').is_none()
-                    || chars.next_if(|c| *c == 'l').is_none()
-                    || chars.next_if(|c| *c == '(').is_none()
-                {
+        for token in tokens {
+            let skip = match self.instructions[token.instruction].effect {
+                Effect::Enable(enables) => {
+                    disabled_by = (!enables).then(|| token.span.clone());
                     continue;
                 }
-
-                let mut numbers = vec![];
-
-                loop {
-                    match chars.peek() {
-                        Some('0'..='9') => {
-                            numbers.push(parse_number(&mut chars));
-                        }
-                        Some(',') => {
-                            chars.next();
+                Effect::Value(_) if disabled_by.is_some() => {
+                    Skip::Disabled(disabled_by.clone().unwrap())
+                }
+                Effect::Value(value) => {
+                    match value(&token.args).and_then(|x| evaluation.total.checked_add(x)) {
+                        Some(total) => {
+                            evaluation.total = total;
                             continue;
                         }
-                        Some(')') => {
-                            chars.next();
-                            break;
-                        }
-                        Some(_x) => continue 'outer, //panic!("Unexpected character in mul: {x}"),
-                        None => continue 'outer,     //panic!("Unexpected end of stream in mul"),
+                        None => Skip::Overflow,
                     }
                 }
+            };
 
-                ops.push(Mul {
-                    a: numbers[0],
-                    b: numbers[1],
-                    enabled,
-                });
-            }
+            evaluation.skipped.push((token.span.clone(), skip));
+        }
 
-            'd' => {
-                fn is_parens(chars: &mut Peekable<impl Iterator<Item = char>>) -> bool {
-                    chars.next_if_eq(&'(').is_some() && chars.next_if_eq(&')').is_some()
-                }
+        evaluation
+    }
 
-                if chars.next_if_eq(&'o').is_none() {
-                    continue;
-                } else if is_parens(&mut chars) {
-                    enabled = true;
-                    continue;
-                } else if chars.next_if_eq(&'n').is_none()
-                    || chars.next_if_eq(&'\'').is_none()
-                    || chars.next_if_eq(&'t').is_none()
-                {
-                    continue;
-                } else if is_parens(&mut chars) {
-                    enabled = false;
-                    continue;
-                }
+    /// `input` with instructions marked as enabled, skipped or toggles, near
+    /// misses flagged, and a count of each rejection reason at the end. Uses
+    /// ANSI colours, or `<+ >` style markers without them
+    fn annotate(&self, input: &str, scan: &Scan, evaluation: &Evaluation, colour: bool) -> String {
+        // Mark and owning span for every byte, tokens painted over near misses
+        let mut marks: Vec<Option<(Mark, usize)>> = vec![None; input.len()];
+        let skipped = evaluation
+            .skipped
+            .iter()
+            .map(|(span, _)| span.start)
+            .collect::<HashSet<_>>();
 
-                enabled = false;
-            }
+        let near_misses = scan.near_misses.iter().map(|x| (&x.span, Mark::NearMiss));
+        let tokens = scan.tokens.iter().map(|token| {
+            let mark = match self.instructions[token.instruction].effect {
+                Effect::Enable(_) => Mark::Toggle,
+                Effect::Value(_) if skipped.contains(&token.span.start) => Mark::Skipped,
+                Effect::Value(_) => Mark::Enabled,
+            };
+            (&token.span, mark)
+        });
+        for (span, mark) in near_misses.chain(tokens) {
+            marks[span.clone()].fill(Some((mark, span.start)));
+        }
+
+        let mut out = String::new();
+        let mut start = 0;
+        while start < input.len() {
+            let end = (start..input.len())
+                .find(|i| marks[*i] != marks[start])
+                .unwrap_or(input.len());
+            let text = &input[start..end];
 
-            _ => {}
+            match marks[start] {
+                Some((mark, _)) if colour => out += &format!("{}{text}\x1b[0m", mark.colour()),
+                Some((mark, _)) => out += &format!("{}{text}>", mark.plain()),
+                None => out += text,
+            }
+            start = end;
+        }
+        if !out.ends_with('\n') {
+            out.push('\n');
         }
-    }
 
-    Some(ops)
-}
+        let mut reasons = HashMap::<_, usize>::new();
+        for near_miss in &scan.near_misses {
+            let name = self.instructions[near_miss.instruction].name;
+            *reasons
+                .entry((name, &near_miss.error.expected))
+                .or_default() += 1;
+        }
+        let mut reasons = reasons.into_iter().collect::<Vec<_>>();
+        reasons.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
 
-fn parse_number(iter: &mut Peekable<impl Iterator<Item = char>>) -> i32 {
-    let mut total = 0;
+        out += &format!("Rejected {} near misses\n", scan.near_misses.len());
+        for ((name, expected), count) in reasons {
+            out += &format!("{count:>5} {name}: expected {expected}\n");
+        }
 
-    from_fn(|| iter.by_ref().next_if(|c| c.is_ascii_digit())).for_each(|x| {
-        let value = x as u32 - '0' as u32;
+        out
+    }
+}
 
-        total *= 10;
+fn generate(rng: &mut Rng, args: &Args) -> String {
+    const NOISE: &[&str] = &[
+        "mul[3,7]",
+        "mul(32,64]",
+        "mul ( 2 , 4 )",
+        "mul(4*",
+        "do_not_",
+        "select()",
+        "who()",
+        "from()",
+        "how()",
+        "{",
+        "%",
+        "'",
+        "?",
+        "<",
+        "]",
+        "#",
+        "@",
+        "!",
+        " ",
+    ];
 
-        total += value;
-    });
+    let length = args.get_or("length", 20000);
+    let mut memory = String::new();
 
-    total as i32
-}
+    while memory.len() < length {
+        match rng.below(10) {
+            0..=2 => memory += &format!("mul({},{})", rng.range(1..1000), rng.range(1..1000)),
+            3 => memory += "do()",
+            4 => memory += "don't()",
+            _ => memory += *rng.choose(NOISE),
+        }
+    }
 
-fn parse_instructions(ops: &[Mul]) -> i32 {
-    ops.iter().filter(|o| o.enabled).map(|o| o.mul()).sum()
+    memory + "\n"
 }
 
 fn main() {
+    let args = Args::from_env();
+    if args.run_generate(generate) {
+        return;
+    }
+
     let input = {
         let mut buf = String::new();
         std::io::stdin().read_to_string(&mut buf).unwrap();
@@ -158,16 +401,37 @@ fn main() {
     };
 
     let (time, result) = timed(|| {
-        let tokens = tokenize_part_1(&input).unwrap();
-        parse_instructions(&tokens)
+        let scanner = Scanner::part_1();
+        scanner.evaluate(&scanner.scan(&input).tokens).total
     });
     println!("Part 1: {result} in {}μs", time.as_micros());
 
-    let (time, result) = timed(|| {
-        let tokens = tokenize_part_2(&input).unwrap();
-        parse_instructions(&tokens)
+    let scanner = Scanner::part_2();
+    let (time, (scan, evaluation)) = timed(|| {
+        let scan = scanner.scan(&input);
+        let evaluation = scanner.evaluate(&scan.tokens);
+        (scan, evaluation)
     });
-    println!("Part 2: {result} in {}μs", time.as_micros());
+    println!("Part 2: {} in {}μs", evaluation.total, time.as_micros());
+
+    if args.flag("explain") {
+        for (span, skip) in &evaluation.skipped {
+            let reason = match skip {
+                Skip::Disabled(by) => format!("disabled by {} at {}", &input[by.clone()], by.start),
+                Skip::Overflow => "overflows".into(),
+            };
+            println!(
+                "Skipped {} at {}: {reason}",
+                &input[span.clone()],
+                span.start
+            );
+        }
+    }
+
+    if args.flag("highlight") {
+        let colour = !args.flag("plain");
+        print!("{}", scanner.annotate(&input, &scan, &evaluation, colour));
+    }
 }
 
 // Part 1: 174103751 in 70μs
@@ -175,15 +439,116 @@ fn main() {
 
 #[cfg(test)]
 mod tests {
-    use crate::{parse_instructions, tokenize_part_1};
+    use crate::{Scanner, Skip};
+
+    const PART_2: &str =
+        "xmul(2,4)&mul[3,7]!^don't()_mul(5,5)+mul(32,64](mul(11,8)undo()?mul(8,5))";
 
     #[test]
     fn part1() {
         let input = "xmul(2,4)%&mul[3,7]!@^do_not_mul(5,5)+mul(32,64]then(mul(11,8)mul(8,5))";
-        let tokens = tokenize_part_1(input).unwrap();
+        let scanner = Scanner::part_1();
+        let tokens = scanner.scan(input).tokens;
         println!("{tokens:#?}");
-        let result = parse_instructions(&tokens);
+        let result = scanner.evaluate(&tokens).total;
 
         assert_eq!(result, 161);
     }
+
+    #[test]
+    fn part2() {
+        let scanner = Scanner::part_2();
+        let tokens = scanner.scan(PART_2).tokens;
+        let evaluation = scanner.evaluate(&tokens);
+
+        assert_eq!(evaluation.total, 48);
+        let mul = scanner.get("mul").unwrap();
+        assert_eq!(tokens.iter().filter(|x| x.instruction == mul).count(), 4);
+
+        assert_eq!(tokens[0].span, 1..9);
+        assert_eq!(tokens[0].args, [2, 4]);
+        assert_eq!(
+            evaluation.skipped,
+            [
+                (28..36, Skip::Disabled(20..27)),
+                (48..57, Skip::Disabled(20..27))
+            ]
+        );
+    }
+
+    #[test]
+    fn registered_instructions() {
+        let scanner = Scanner::part_2()
+            .instruction("add", 3, 2, |x| Some(x.iter().sum()))
+            .instruction("neg", 1, 4, |x| Some(-x[0]));
+
+        let tokens = scanner
+            .scan("add(1,2,3)add(1,2)neg(12345)neg(7)mul(1000,1)mul(1,1)")
+            .tokens;
+        let names = tokens
+            .iter()
+            .map(|x| scanner.instructions[x.instruction].name)
+            .collect::<Vec<_>>();
+        assert_eq!(names, ["add", "neg", "mul"]);
+        assert_eq!(scanner.evaluate(&tokens).total, 6 - 7 + 1);
+
+        let scanner = Scanner::new().instruction("mul", 2, 3, |x| {
+            x[0].checked_mul(x[1]).map(|x| x * (i64::MAX / 998_001))
+        });
+        let evaluation = scanner.evaluate(&scanner.scan(&"mul(999,999)".repeat(2)).tokens);
+        assert_eq!(evaluation.skipped, [(12..24, Skip::Overflow)]);
+
+        let scanner = Scanner::new().instruction("neg", 1, 20, |x| Some(-x[0]));
+        let scan = scanner.scan("neg(9999999999999999999)neg(9223372036854775807)");
+        assert_eq!(scan.tokens.len(), 1);
+        assert_eq!(scan.tokens[0].args, [i64::MAX]);
+        assert_eq!(scan.near_misses.len(), 1);
+        assert_eq!(scan.near_misses[0].span, 0..5);
+        assert_eq!(
+            scan.near_misses[0].error.expected,
+            "an argument up to 9223372036854775807"
+        );
+    }
+
+    #[test]
+    fn near_misses() {
+        let scanner = Scanner::part_2();
+        let scan = scanner.scan("mul[3,7]mul(32,64]mul ( 2 , 4 )mul(4*do_not_mul(1234,5)don't(");
+
+        let spans = scan
+            .near_misses
+            .iter()
+            .map(|x| (x.span.clone(), x.error.expected.as_str()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            spans,
+            [
+                (0..4, "\"(\""),
+                (8..18, "\")\""),
+                (18..22, "\"(\""),
+                (31..37, "\",\""),
+                (44..49, "1 to 3 digits"),
+                (55..61, "\")\""),
+            ]
+        );
+        assert!(scan.tokens.is_empty());
+    }
+
+    #[test]
+    fn annotate() {
+        let scanner = Scanner::part_2();
+        let scan = scanner.scan(PART_2);
+        let evaluation = scanner.evaluate(&scan.tokens);
+
+        assert_eq!(
+            scanner.annotate(PART_2, &scan, &evaluation, false),
+            "x<+mul(2,4)>&<!mul[>3,7]!^<~don't()>_<-mul(5,5)>+<!mul(32,64]>(<-mul(11,8)>un<~do()>?<+mul(8,5)>)\n\
+             Rejected 2 near misses\n    \
+             1 mul: expected \"(\"\n    \
+             1 mul: expected \")\"\n"
+        );
+
+        let coloured = scanner.annotate("mul(1,2)", &scanner.scan("mul(1,2)"), &evaluation, true);
+        assert!(coloured.starts_with("\x1b[32mmul(1,2)\x1b[0m\n"));
+    }
 }
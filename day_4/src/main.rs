@@ -1,149 +1,437 @@
-use common::{read_stdin, timed};
-
-trait Vec2d<T> {
-    fn get(&self, x: usize, y: usize) -> Option<&T>;
-}
-impl<T> Vec2d<T> for Vec<Vec<T>> {
-    fn get(&self, x: usize, y: usize) -> Option<&T> {
-        self.as_slice().get(y).and_then(|row| row.as_slice().get(x))
-    }
-}
-
-type Array = Vec<Vec<char>>;
-
-fn string_to_array(input: &str) -> Array {
-    input.lines().map(|x| x.chars().collect()).collect()
-}
-
-fn new_pos(x: usize, y: usize, delta_x: isize, delta_y: isize) -> (usize, usize) {
-    let new_x = x as isize + delta_x;
-    let new_y = y as isize + delta_y;
-    (new_x as usize, new_y as usize)
-}
-
-fn search_all_directions(data: &Array, x: usize, y: usize) -> i32 {
-    let vectors = [
-        // Right
-        (1, 0),
-        // Up
-        (0, -1),
-        // Down
-        (0, 1),
-        // Left
-        (-1, 0),
-        // Up-Left
-        (-1, -1),
-        // Up-Right
-        (1, -1),
-        // Down-Left
-        (-1, 1),
-        // Down-Right
-        (1, 1),
-    ];
-
-    let mut count = 0;
-
-    for (delta_x, delta_y) in vectors {
-        let m_pos = new_pos(x, y, delta_x, delta_y);
-        let a_pos = new_pos(x, y, delta_x * 2, delta_y * 2);
-        let s_pos = new_pos(x, y, delta_x * 3, delta_y * 3);
-        if data.get(m_pos.0, m_pos.1) == Some(&'M')
-            && data.get(a_pos.0, a_pos.1) == Some(&'A')
-            && data.get(s_pos.0, s_pos.1) == Some(&'S')
-        {
-            count += 1;
+use common::{params::Schema, read_stdin, timed, vectors, Args, Grid, Pos, Rng};
+use std::collections::VecDeque;
+
+fn parse_grid(input: &str) -> Grid<char> {
+    Grid::new(input.lines().map(|x| x.chars().collect()).collect())
+}
+
+/// Cell at `p`, wrapping around the edges if `wrap` is set
+fn cell(grid: &Grid<char>, p: Pos, wrap: bool) -> Option<char> {
+    if wrap {
+        let p = Pos::new(p.x.rem_euclid(grid.width), p.y.rem_euclid(grid.height));
+        Some(grid[p])
+    } else {
+        grid.get(&p).copied()
+    }
+}
+
+/// Word read from `start` along `direction`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct WordMatch {
+    start: Pos,
+    direction: Pos,
+}
+
+/// Every occurrence of `word` in any of the eight directions. Palindromes are
+/// found once in each direction they read in
+fn find_word(grid: &Grid<char>, word: &str, wrap: bool) -> Vec<WordMatch> {
+    let word = word.chars().collect::<Vec<_>>();
+    let Some(&first) = word.first() else {
+        return vec![];
+    };
+
+    let mut matches = vec![];
+    for (c, start) in grid.iter() {
+        if *c != first {
+            continue;
+        }
+
+        for direction in vectors::ALL {
+            let found = (1..word.len())
+                .all(|i| cell(grid, start + direction * i as isize, wrap) == Some(word[i]));
+            if found {
+                matches.push(WordMatch { start, direction });
+            }
         }
     }
 
-    count
+    matches
 }
 
-fn find_matches(data: &Array) -> i32 {
-    let height = data.len();
-    let width = data[0].len();
+/// Aho-Corasick automaton over a set of words, so one pass over a sequence
+/// finds every occurrence of all of them, overlapping ones included
+struct Automaton {
+    /// Distinct characters of the words, sorted
+    alphabet: Vec<char>,
+    /// Next state for each state and alphabet index
+    transitions: Vec<Vec<usize>>,
+    /// Words ending at each state, including those ending at its suffixes
+    outputs: Vec<Vec<usize>>,
+    /// Length of the longest word
+    longest: usize,
+}
+impl Automaton {
+    /// Empty words never match
+    fn new(words: &[&str]) -> Self {
+        let mut alphabet = words.iter().flat_map(|w| w.chars()).collect::<Vec<_>>();
+        alphabet.sort();
+        alphabet.dedup();
+
+        // Trie, where a missing edge is `usize::MAX`
+        let mut transitions = vec![vec![usize::MAX; alphabet.len()]];
+        let mut outputs = vec![vec![]];
+        for (i, word) in words.iter().enumerate().filter(|(_, w)| !w.is_empty()) {
+            let mut state = 0;
+            for c in word.chars() {
+                let c = alphabet.binary_search(&c).unwrap();
+                if transitions[state][c] == usize::MAX {
+                    transitions[state][c] = transitions.len();
+                    transitions.push(vec![usize::MAX; alphabet.len()]);
+                    outputs.push(vec![]);
+                }
+                state = transitions[state][c];
+            }
+            outputs[state].push(i);
+        }
 
-    let mut count = 0;
+        // Fill in missing edges from the failure links, breadth first so a
+        // state's failure target is complete before the state itself
+        let mut fail = vec![0; transitions.len()];
+        let mut queue = VecDeque::new();
+        for next in &mut transitions[0] {
+            match *next {
+                usize::MAX => *next = 0,
+                _ => queue.push_back(*next),
+            }
+        }
+        while let Some(state) = queue.pop_front() {
+            let suffix = outputs[fail[state]].clone();
+            outputs[state].extend(suffix);
+
+            let fallbacks = transitions[fail[state]].clone();
+            for (next, fallback) in transitions[state].iter_mut().zip(fallbacks) {
+                match *next {
+                    usize::MAX => *next = fallback,
+                    _ => {
+                        fail[*next] = fallback;
+                        queue.push_back(*next);
+                    }
+                }
+            }
+        }
 
-    for col in 0..width {
-        for row in 0..height {
-            if data.get(col, row) == Some(&'X') {
-                let matches = search_all_directions(data, col, row);
-                count += matches;
+        Self {
+            alphabet,
+            transitions,
+            outputs,
+            longest: words.iter().map(|w| w.chars().count()).max().unwrap_or(0),
+        }
+    }
+
+    fn step(&self, state: usize, c: char) -> usize {
+        match self.alphabet.binary_search(&c) {
+            Ok(c) => self.transitions[state][c],
+            Err(_) => 0,
+        }
+    }
+}
+
+/// Number of occurrences of each word, counted like [`find_word`] does but
+/// reading every ray of the grid only once for all the words
+fn find_words(grid: &Grid<char>, words: &[&str], wrap: bool) -> Vec<usize> {
+    let automaton = Automaton::new(words);
+    let lengths = words.iter().map(|w| w.chars().count()).collect::<Vec<_>>();
+    let mut counts = vec![0; words.len()];
+
+    for direction in vectors::ALL {
+        // A ray starts at every cell without a predecessor. When wrapping,
+        // rays are cycles and start at the first cell of the cycle reached
+        let mut visited = vec![vec![false; grid.width as usize]; grid.height as usize];
+        for (_, start) in grid.iter() {
+            if visited[start.y as usize][start.x as usize]
+                || (!wrap && grid.is_inside(&(start - direction)))
+            {
+                continue;
+            }
+
+            let mut ray = vec![];
+            let mut p = start;
+            while let Some(c) = cell(grid, p, wrap) {
+                let seen = &mut visited[p.y as usize][p.x as usize];
+                if *seen {
+                    break;
+                }
+                *seen = true;
+                ray.push(c);
+                p = p + direction;
+                if wrap {
+                    p = Pos::new(p.x.rem_euclid(grid.width), p.y.rem_euclid(grid.height));
+                }
+            }
+
+            // Going round a cycle again finds the words that cross its start,
+            // but only those starting in the first lap are new
+            let len = ray.len();
+            let extra = if wrap {
+                automaton.longest.saturating_sub(1)
+            } else {
+                0
+            };
+            let mut state = 0;
+            for (i, c) in ray.iter().cycle().take(len + extra).enumerate() {
+                state = automaton.step(state, *c);
+                for &word in &automaton.outputs[state] {
+                    if i + 1 - lengths[word] < len {
+                        counts[word] += 1;
+                    }
+                }
             }
         }
     }
 
-    count
+    counts
 }
 
-fn search_all_directions_2(data: &Array, x: usize, y: usize) -> bool {
-    struct Diagonals {
-        up_left: char,
-        up_right: char,
-        down_left: char,
-        down_right: char,
+/// One of the eight rotations and reflections of a template
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+struct Transform {
+    /// Mirrored left to right, before rotating
+    reflected: bool,
+    /// Quarter turns clockwise
+    turns: u8,
+}
+impl Transform {
+    fn all() -> impl Iterator<Item = Self> {
+        [false, true]
+            .into_iter()
+            .flat_map(|reflected| (0..4).map(move |turns| Self { reflected, turns }))
     }
 
-    fn get_diagonals(data: &Array, x: usize, y: usize) -> Option<Diagonals> {
-        let up_left = new_pos(x, y, -1, -1);
-        let up_right = new_pos(x, y, 1, -1);
-        let down_left = new_pos(x, y, -1, 1);
-        let down_right = new_pos(x, y, 1, 1);
+    fn apply(self, p: Pos) -> Pos {
+        let p = if self.reflected {
+            Pos::new(-p.x, p.y)
+        } else {
+            p
+        };
+        (0..self.turns).fold(p, |p, _| Pos::new(-p.y, p.x))
+    }
+}
 
-        Some(Diagonals {
-            up_left: data.get(up_left.0, up_left.1).cloned()?,
-            up_right: data.get(up_right.0, up_right.1).cloned()?,
-            down_left: data.get(down_left.0, down_left.1).cloned()?,
-            down_right: data.get(down_right.0, down_right.1).cloned()?,
-        })
+/// 2D pattern of characters, where wildcard cells match anything, even
+/// outside the grid
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Template {
+    /// Cells that must match, sorted, with the top-left corner at 0,0
+    cells: Vec<(Pos, char)>,
+}
+impl Template {
+    /// `/`-separated rows, such as `M.S/.A./M.S`
+    fn parse(rows: &str, wildcard: char) -> Self {
+        let cells = rows
+            .split('/')
+            .enumerate()
+            .flat_map(|(y, row)| {
+                row.chars()
+                    .enumerate()
+                    .filter(|(_, c)| *c != wildcard)
+                    .map(move |(x, c)| ((x, y).into(), c))
+            })
+            .collect();
+
+        Self::normalized(cells)
     }
 
-    fn has_down_right_diagonal(diagonals: &Diagonals) -> bool {
-        (diagonals.up_left == 'M' && diagonals.down_right == 'S')
-            || (diagonals.up_left == 'S' && diagonals.down_right == 'M')
+    fn normalized(mut cells: Vec<(Pos, char)>) -> Self {
+        let min_x = cells.iter().map(|(p, _)| p.x).min().unwrap_or(0);
+        let min_y = cells.iter().map(|(p, _)| p.y).min().unwrap_or(0);
+        for (p, _) in &mut cells {
+            *p = *p - Pos::new(min_x, min_y);
+        }
+
+        cells.sort_by_key(|(p, c)| (p.y, p.x, *c));
+        Self { cells }
     }
 
-    fn has_up_right_diagonal(diagonals: &Diagonals) -> bool {
-        (diagonals.down_left == 'M' && diagonals.up_right == 'S')
-            || (diagonals.down_left == 'S' && diagonals.up_right == 'M')
+    fn transformed(&self, transform: Transform) -> Self {
+        Self::normalized(
+            self.cells
+                .iter()
+                .map(|(p, c)| (transform.apply(*p), *c))
+                .collect(),
+        )
     }
 
-    let diagonals = get_diagonals(data, x, y);
-    if let Some(diagonals) = diagonals {
-        if has_up_right_diagonal(&diagonals) && has_down_right_diagonal(&diagonals) {
-            return true;
+    /// Distinct orientations, so symmetric templates aren't counted twice
+    fn orientations(&self) -> Vec<(Transform, Self)> {
+        let mut orientations: Vec<(Transform, Self)> = vec![];
+        for transform in Transform::all() {
+            let template = self.transformed(transform);
+            if orientations.iter().all(|(_, x)| *x != template) {
+                orientations.push((transform, template));
+            }
         }
+        orientations
     }
 
-    false
+    fn size(&self) -> Pos {
+        let width = self.cells.iter().map(|(p, _)| p.x + 1).max().unwrap_or(0);
+        let height = self.cells.iter().map(|(p, _)| p.y + 1).max().unwrap_or(0);
+        Pos::new(width, height)
+    }
 }
 
-fn find_matches_2(data: &Array) -> i32 {
-    let height = data.len();
-    let width = data[0].len();
+/// Template placed with its top-left corner at `origin`, after `transform`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct TemplateMatch {
+    origin: Pos,
+    transform: Transform,
+}
 
-    let mut count = 0;
+/// Every placement of `template` in any distinct orientation. Each
+/// orientation is anchored on its rarest character, so it is only tried
+/// where that character appears
+fn find_template(grid: &Grid<char>, template: &Template, wrap: bool) -> Vec<TemplateMatch> {
+    let mut chars = template.cells.iter().map(|(_, c)| *c).collect::<Vec<_>>();
+    chars.sort();
+    chars.dedup();
+    let counts = chars
+        .iter()
+        .map(|c| grid.data.iter().flatten().filter(|x| *x == c).count())
+        .collect::<Vec<_>>();
+    let count = |c: &char| counts[chars.binary_search(c).unwrap()];
+
+    let Some(&(_, first)) = template.cells.first() else {
+        // Only wildcards, which match everywhere
+        let origins = grid.iter().map(|(_, origin)| origin);
+        return origins
+            .map(|origin| TemplateMatch {
+                origin,
+                transform: Transform::default(),
+            })
+            .collect();
+    };
+
+    // Cells relative to the anchor, and the anchor's offset from the origin
+    let orientations = template
+        .orientations()
+        .into_iter()
+        .map(|(transform, template)| {
+            let (anchor, c) = template
+                .cells
+                .iter()
+                .copied()
+                .min_by_key(|(_, c)| count(c))
+                .unwrap_or((Pos::new(0, 0), first));
+            let cells = template
+                .cells
+                .iter()
+                .filter(|(p, _)| *p != anchor)
+                .map(|(p, c)| (*p - anchor, *c))
+                .collect::<Vec<_>>();
+
+            // Anchor positions where the template fits without wrapping
+            let size = template.size();
+            let xs = anchor.x..grid.width - size.x + anchor.x + 1;
+            let ys = anchor.y..grid.height - size.y + anchor.y + 1;
+
+            (transform, anchor, c, cells, xs, ys)
+        })
+        .collect::<Vec<_>>();
 
-    for col in 0..width {
-        for row in 0..height {
-            if data.get(col, row) == Some(&'A') && search_all_directions_2(data, col, row) {
-                count += 1;
+    let mut matches = vec![];
+    for (c, p) in grid.iter() {
+        for (transform, anchor, anchor_char, cells, xs, ys) in &orientations {
+            if c != anchor_char {
+                continue;
+            }
+
+            let found = if wrap {
+                cells
+                    .iter()
+                    .all(|(offset, c)| cell(grid, p + *offset, true) == Some(*c))
+            } else {
+                xs.contains(&p.x)
+                    && ys.contains(&p.y)
+                    && cells.iter().all(|(offset, c)| {
+                        let p = p + *offset;
+                        grid.data[p.y as usize][p.x as usize] == *c
+                    })
+            };
+
+            if found {
+                let origin = p - *anchor;
+                let origin = if wrap {
+                    Pos::new(
+                        origin.x.rem_euclid(grid.width),
+                        origin.y.rem_euclid(grid.height),
+                    )
+                } else {
+                    origin
+                };
+
+                matches.push(TemplateMatch {
+                    origin,
+                    transform: *transform,
+                });
             }
         }
     }
 
-    count
+    matches
+}
+
+fn schema() -> Schema {
+    Schema::new()
+        .param("word", String::from("XMAS"), "word searched for in part 1")
+        .param(
+            "template",
+            String::from("M.S/.A./M.S"),
+            "pattern for part 2, rows separated by /",
+        )
+        .param("wildcard", '.', "template cell that matches anything")
+        .param("wrap", false, "whether matches wrap around the edges")
+        .param(
+            "words",
+            String::new(),
+            "file of more words to count, one per line",
+        )
+}
+
+fn generate(rng: &mut Rng, args: &Args) -> String {
+    let width = args.get_or("width", 140);
+    let height = args.get_or("height", width);
+
+    (0..height)
+        .map(|_| {
+            let row = (0..width).map(|_| *rng.choose(&['X', 'M', 'A', 'S']));
+            row.chain(['\n']).collect::<String>()
+        })
+        .collect()
 }
 
 fn main() {
+    let args = Args::from_env();
+    if args.run_generate(generate) {
+        return;
+    }
+
+    let params = args.params(&schema());
+    let word: String = params.get("word");
+    let template = Template::parse(&params.get::<String>("template"), params.get("wildcard"));
+    let wrap = params.get("wrap");
+
     let input = read_stdin();
-    let array = string_to_array(&input);
+    let grid = parse_grid(&input);
+
+    let (time, matches) = timed(|| find_word(&grid, &word, wrap));
+    println!("Part 1: {} in {}μs", matches.len(), time.as_micros());
 
-    let (time, ranges) = timed(|| find_matches(&array));
-    println!("Part 1: {} in {}μs", ranges, time.as_micros());
+    let (time, matches) = timed(|| find_template(&grid, &template, wrap));
+    println!("Part 2: {} in {}μs", matches.len(), time.as_micros());
 
-    let (time, matches) = timed(|| find_matches_2(&array));
-    println!("Part 2: {matches} in {}μs", time.as_micros());
+    let path: String = params.get("words");
+    if !path.is_empty() {
+        let words =
+            std::fs::read_to_string(&path).unwrap_or_else(|e| panic!("Can't read {path}: {e}"));
+        let words = words.split_whitespace().collect::<Vec<_>>();
+
+        let (time, counts) = timed(|| find_words(&grid, &words, wrap));
+        let total = counts.iter().sum::<usize>();
+        println!("Words: {total} in {}μs", time.as_micros());
+        for (word, count) in words.iter().zip(counts) {
+            println!("  {word}: {count}");
+        }
+    }
 }
 
 // Part 1: 2547 in 430μs
@@ -151,34 +439,106 @@ fn main() {
 
 #[cfg(test)]
 mod tests {
-    use crate::{find_matches, find_matches_2, string_to_array, Vec2d};
+    use crate::{find_template, find_word, find_words, parse_grid, Template, Transform};
+    use common::Pos;
+
+    const EXAMPLE: &str = "MMMSXXMASM\nMSAMXMSMSA\nAMXSXMAAMM\nMSAMASMSMX\nXMASAMXAMM\nXXAMMXXAMA\nSMSMSASXSS\nSAXAMASAAA\nMAMMMXMMMM\nMXMXAXMASX\n";
 
     #[test]
     fn two_dimensional_array_test() {
         let input = "abc\ndef\nghi";
-        let array = string_to_array(input);
+        let grid = parse_grid(input);
 
-        assert_eq!(array[1][0], 'd');
-        assert_eq!(array.get(0, 1), Some(&'d'));
+        assert_eq!(grid.data[1][0], 'd');
+        assert_eq!(grid.get(&Pos::new(0, 1)), Some(&'d'));
 
-        assert_eq!(array, [['a', 'b', 'c'], ['d', 'e', 'f'], ['g', 'h', 'i'],]);
+        assert_eq!(
+            grid.data,
+            [['a', 'b', 'c'], ['d', 'e', 'f'], ['g', 'h', 'i'],]
+        );
     }
 
     #[test]
     fn part1() {
-        let input = "MMMSXXMASM\nMSAMXMSMSA\nAMXSXMAAMM\nMSAMASMSMX\nXMASAMXAMM\nXXAMMXXAMA\nSMSMSASXSS\nSAXAMASAAA\nMAMMMXMMMM\nMXMXAXMASX\n";
-        let array = string_to_array(input);
-        let matches = find_matches(&array);
+        let grid = parse_grid(EXAMPLE);
+        let matches = find_word(&grid, "XMAS", false);
 
-        assert_eq!(matches, 18);
+        assert_eq!(matches.len(), 18);
     }
 
     #[test]
     fn part2() {
-        let input = "MMMSXXMASM\nMSAMXMSMSA\nAMXSXMAAMM\nMSAMASMSMX\nXMASAMXAMM\nXXAMMXXAMA\nSMSMSASXSS\nSAXAMASAAA\nMAMMMXMMMM\nMXMXAXMASX\n";
-        let array = string_to_array(input);
-        let matches = find_matches_2(&array);
+        let grid = parse_grid(EXAMPLE);
+        let matches = find_template(&grid, &Template::parse("M.S/.A./M.S", '.'), false);
+
+        assert_eq!(matches.len(), 9);
+    }
+
+    #[test]
+    fn words() {
+        let grid = parse_grid("ABA\nXBX\nABA\n");
+
+        // Palindromes read the same both ways, so they are found twice
+        let matches = find_word(&grid, "ABA", false);
+        assert_eq!(matches.len(), 4 * 2);
+        assert!(matches
+            .iter()
+            .any(|m| m.start == Pos::new(2, 2) && m.direction == Pos::new(-1, -1)));
+
+        assert_eq!(find_word(&grid, "XX", false).len(), 0);
+        assert_eq!(find_word(&grid, "XX", true).len(), 2);
+        assert_eq!(find_word(&grid, "", false), []);
+    }
+
+    #[test]
+    fn templates() {
+        let l = Template::parse("A./AB", '.');
+        let orientations = l.orientations();
+        assert_eq!(orientations.len(), 8);
+        assert_eq!(
+            orientations[1].0,
+            Transform {
+                reflected: false,
+                turns: 1
+            }
+        );
+        assert_eq!(
+            orientations[1].1,
+            Template::parse("AA/B.", '.'),
+            "a quarter turn clockwise"
+        );
+
+        assert_eq!(Template::parse("A.A/.A./A.A", '.').orientations().len(), 1);
+
+        let grid = parse_grid("BA\n.A\n");
+        let matches = find_template(&grid, &l, false);
+        assert_eq!(matches.len(), 1);
+        assert_eq!(matches[0].origin, Pos::new(0, 0));
+        assert_eq!(find_template(&grid, &l, true).len(), 4);
+    }
+
+    #[test]
+    fn many_words() {
+        let grid = parse_grid(EXAMPLE);
+        let words = [
+            "XMAS", "MAS", "SAM", "AMA", "MM", "X", "XMASXMAS", "", "XMAS",
+        ];
+        for wrap in [false, true] {
+            let expected = words
+                .iter()
+                .map(|w| find_word(&grid, w, wrap).len())
+                .collect::<Vec<_>>();
+            assert_eq!(find_words(&grid, &words, wrap), expected, "wrap {wrap}");
+        }
+
+        // Overlapping matches, and palindromes once in each direction
+        let grid = parse_grid("AAA\n");
+        assert_eq!(find_words(&grid, &["AA", "AAA", "B"], false), [4, 2, 0]);
 
-        assert_eq!(matches, 9);
+        // Words longer than a wrapped row go round it more than once, along
+        // the six directions that move sideways in a single row
+        let grid = parse_grid("AB\n");
+        assert_eq!(find_words(&grid, &["ABAB", "BABA"], true), [6, 6]);
+        assert_eq!(find_word(&grid, "ABAB", true).len(), 6);
     }
 }
@@ -1,7 +1,15 @@
-use common::{read_stdin, timed};
-use std::collections::{HashMap, HashSet};
-
-type Requirements = HashMap<i32, HashSet<i32>>;
+use common::{
+    bits::BitSet,
+    graph::{Graph, NodeId},
+    read_stdin, timed, Args, Rng,
+};
+use std::{
+    cmp::Reverse,
+    collections::{HashMap, HashSet},
+};
+
+/// Edge `x -> y` when page `x` must come before page `y`
+type Requirements = Graph<i32>;
 type Update = Vec<i32>;
 
 #[derive(Debug)]
@@ -13,7 +21,7 @@ struct Input {
 fn parse_input(input: &str) -> Input {
     let mut lines = input.lines();
 
-    let mut requirements = HashMap::new();
+    let mut requirements = Graph::directed();
     let mut updates = vec![];
 
     for line in lines.by_ref() {
@@ -26,12 +34,7 @@ fn parse_input(input: &str) -> Input {
         let x: i32 = x.parse().unwrap();
         let y: i32 = y.parse().unwrap();
 
-        requirements
-            .entry(x)
-            .and_modify(|set: &mut HashSet<i32>| {
-                set.insert(y);
-            })
-            .or_insert(HashSet::from_iter([y]));
+        requirements.add_edge(x, y);
     }
 
     for line in lines {
@@ -40,6 +43,11 @@ fn parse_input(input: &str) -> Input {
             .map(|x| x.parse::<i32>().unwrap())
             .collect::<Vec<_>>();
 
+        // Pages without rules still need a node to be sorted
+        update.iter().for_each(|x| {
+            requirements.intern(*x);
+        });
+
         updates.push(update);
     }
 
@@ -53,16 +61,13 @@ fn is_correctly_ordered(requirements: &Requirements, update: &Update) -> bool {
     let mut encountered = HashSet::new();
 
     for x in update {
-        match requirements.get(x) {
-            None => {}
-            Some(reqs) => {
-                for r in reqs {
-                    if encountered.contains(r) {
-                        return false;
-                    }
+        if let Some(id) = requirements.id(x) {
+            for r in requirements.neighbors(id) {
+                if encountered.contains(requirements.node(r)) {
+                    return false;
                 }
             }
-        };
+        }
 
         encountered.insert(*x);
     }
@@ -84,39 +89,358 @@ fn get_ordered_middles(input: &Input) -> Vec<i32> {
         .collect()
 }
 
-fn reorder_incorrect(requirements: &Requirements, update: &Update) -> Update {
-    let mut update = update.clone();
-
-    update.sort_by(|a, b| {
-        let req = requirements.get(a).map(|s| s.contains(b)).unwrap_or(false);
-        if req {
-            std::cmp::Ordering::Less
-        } else {
-            std::cmp::Ordering::Equal
-        }
-    });
+/// Pages whose rules contradict each other, each before the next and the
+/// last before the first
+type Cycle = Vec<i32>;
 
+fn update_ids<'a>(
+    requirements: &'a Requirements,
+    update: &'a Update,
+) -> impl Iterator<Item = NodeId> + 'a {
     update
+        .iter()
+        .map(|x| requirements.id(x).expect("page was interned when parsing"))
+}
+
+fn pages(requirements: &Requirements, ids: Vec<NodeId>) -> Vec<i32> {
+    ids.into_iter().map(|id| *requirements.node(id)).collect()
+}
+
+/// Pages in an order following every rule between them. When several fit,
+/// pages keep their update order as far as possible
+fn reorder_incorrect(requirements: &Requirements, update: &Update) -> Result<Update, Cycle> {
+    requirements
+        .toposort_of(update_ids(requirements, update))
+        .map(|order| pages(requirements, order))
+        .map_err(|cycle| pages(requirements, cycle))
+}
+
+/// Number of orders of the update's pages that follow every rule. `None` if
+/// there are too many to count
+fn count_orderings(requirements: &Requirements, update: &Update) -> Result<Option<u128>, Cycle> {
+    requirements
+        .count_toposorts_of(update_ids(requirements, update))
+        .map_err(|cycle| pages(requirements, cycle))
+}
+
+/// Up to `limit` orders of the update's pages that follow every rule, the
+/// first being the one [`reorder_incorrect`] picks
+fn orderings(
+    requirements: &Requirements,
+    update: &Update,
+    limit: usize,
+) -> Result<Vec<Update>, Cycle> {
+    let orders = requirements
+        .toposorts_of(update_ids(requirements, update), limit)
+        .map_err(|cycle| pages(requirements, cycle))?;
+
+    Ok(orders
+        .into_iter()
+        .map(|order| pages(requirements, order))
+        .collect())
 }
 
-fn get_unordered_middles(input: &Input) -> Vec<i32> {
+/// Middles of the reordered incorrect updates, or the cycle that prevents
+/// reordering one
+fn get_unordered_middles(input: &Input) -> Vec<Result<i32, Cycle>> {
     input
         .updates
         .iter()
         .filter(|update| !is_correctly_ordered(&input.requirements, update))
         .map(|update| reorder_incorrect(&input.requirements, update))
-        .map(|x| get_middle(&x))
+        .map(|x| x.map(|x| get_middle(&x)))
         .collect()
 }
 
+/// Rule `before|after` broken by an update, which has `after` at
+/// `positions.0` and `before` later at `positions.1`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Violation {
+    before: i32,
+    after: i32,
+    positions: (usize, usize),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Explanation {
+    violations: Vec<Violation>,
+    /// Fewest pages that need moving, in update order
+    moves: Vec<i32>,
+    /// Order after moving them, with every other page kept in place
+    fixed: Update,
+}
+
+fn violations(requirements: &Requirements, update: &Update) -> Vec<Violation> {
+    let ids = update_ids(requirements, update).collect::<Vec<_>>();
+
+    let mut violations = vec![];
+    for (j, before) in ids.iter().enumerate() {
+        for (i, after) in ids[..j].iter().enumerate() {
+            if requirements.has_edge(*before, *after) {
+                violations.push(Violation {
+                    before: update[j],
+                    after: update[i],
+                    positions: (i, j),
+                });
+            }
+        }
+    }
+    violations
+}
+
+/// Broken rules and the fewest moves that fix them
+fn explain(requirements: &Requirements, update: &Update) -> Result<Explanation, Cycle> {
+    reorder_incorrect(requirements, update)?;
+
+    let ids = update_ids(requirements, update).collect::<Vec<_>>();
+    let n = ids.len();
+
+    // Pages that must come before each page, directly or through others
+    let mut before = ids
+        .iter()
+        .map(|x| {
+            let before = ids.iter().enumerate();
+            before
+                .filter(|(_, y)| requirements.has_edge(**y, *x))
+                .map(|(i, _)| i)
+                .collect::<BitSet>()
+        })
+        .collect::<Vec<_>>();
+    for k in 0..n {
+        let through = before[k].clone();
+        for earlier in &mut before {
+            if earlier.contains(k) {
+                earlier.union_with(&through);
+            }
+        }
+    }
+
+    // Pages left in place must already be in a valid order, so no page may
+    // have to come before an earlier one. That relation is a partial order,
+    // and the most pages that can stay are its largest antichain, found from
+    // a maximum matching and König's theorem
+    let conflicts = |i: usize, j: usize| i < j && before[i].contains(j);
+    let mut matched = vec![None; n];
+    for i in 0..n {
+        augment(i, &conflicts, &mut vec![false; n], &mut matched);
+    }
+
+    // Left and right sides reachable by alternating paths from unmatched
+    // left vertices
+    let mut reachable = (vec![false; n], vec![false; n]);
+    let mut stack = (0..n)
+        .filter(|i| !matched.contains(&Some(*i)))
+        .collect::<Vec<_>>();
+    while let Some(i) = stack.pop() {
+        if std::mem::replace(&mut reachable.0[i], true) {
+            continue;
+        }
+        for (j, partner) in matched.iter().enumerate() {
+            if conflicts(i, j) && !std::mem::replace(&mut reachable.1[j], true) {
+                stack.extend(*partner);
+            }
+        }
+    }
+    let kept = (0..n).filter(|i| reachable.0[*i] && !reachable.1[*i]);
+    let kept = kept.collect::<BitSet>();
+
+    // Kept pages go in their current order, the others wherever they fit
+    // first
+    let mut placed = BitSet::with_capacity(n);
+    let mut fixed = vec![];
+    while fixed.len() < n {
+        let i = (0..n)
+            .find(|i| {
+                let mut earlier_kept = (0..*i).filter(|j| kept.contains(*j));
+                !placed.contains(*i)
+                    && before[*i].is_subset(&placed)
+                    && (!kept.contains(*i) || earlier_kept.all(|j| placed.contains(j)))
+            })
+            .expect("kept pages are in a valid order");
+        placed.insert(i);
+        fixed.push(update[i]);
+    }
+
+    Ok(Explanation {
+        violations: violations(requirements, update),
+        moves: (0..n)
+            .filter(|i| !kept.contains(*i))
+            .map(|i| update[i])
+            .collect(),
+        fixed,
+    })
+}
+
+/// Finds an augmenting path from left vertex `i`, for Kuhn's matching
+fn augment(
+    i: usize,
+    edge: &impl Fn(usize, usize) -> bool,
+    seen: &mut [bool],
+    matched: &mut [Option<usize>],
+) -> bool {
+    for j in 0..matched.len() {
+        if edge(i, j)
+            && !std::mem::replace(&mut seen[j], true)
+            && matched[j].is_none_or(|k| augment(k, edge, seen, matched))
+        {
+            matched[j] = Some(i);
+            return true;
+        }
+    }
+    false
+}
+
+/// Each broken rule with the number of updates breaking it, most often
+/// broken first
+fn violation_summary(input: &Input) -> Vec<((i32, i32), usize)> {
+    let mut counts = HashMap::new();
+    for update in &input.updates {
+        let mut rules = violations(&input.requirements, update)
+            .into_iter()
+            .map(|v| (v.before, v.after))
+            .collect::<Vec<_>>();
+        rules.sort();
+        rules.dedup();
+
+        for rule in rules {
+            *counts.entry(rule).or_insert(0) += 1;
+        }
+    }
+
+    let mut summary = counts.into_iter().collect::<Vec<_>>();
+    summary.sort_by_key(|(rule, count)| (Reverse(*count), *rule));
+    summary
+}
+
+fn generate(rng: &mut Rng, args: &Args) -> String {
+    let pages = args.get_or("pages", 49usize).max(3);
+    let updates = args.get_or("updates", 200);
+
+    // Every pair of pages gets a rule, so any subset has a unique correct order
+    let mut order = (10..10 + pages as i32).collect::<Vec<_>>();
+    rng.shuffle(&mut order);
+
+    // Dropping rules allows several orders, flipping them can make cycles
+    let drop = args.get_or("drop", 0.0);
+    let flip = args.get_or("flip", 0.0);
+
+    let mut rules = vec![];
+    for (i, x) in order.iter().enumerate() {
+        for y in &order[i + 1..] {
+            if drop > 0.0 && rng.chance(drop) {
+                continue;
+            }
+
+            if flip > 0.0 && rng.chance(flip) {
+                rules.push(format!("{y}|{x}\n"));
+            } else {
+                rules.push(format!("{x}|{y}\n"));
+            }
+        }
+    }
+    rng.shuffle(&mut rules);
+
+    let updates = (0..updates).map(|_| {
+        let len = (3 + rng.index(pages.min(23) - 2)) | 1;
+
+        let mut update = order.clone();
+        rng.shuffle(&mut update);
+        update.truncate(len);
+
+        if rng.chance(0.5) {
+            update.sort_by_key(|x| order.iter().position(|o| o == x));
+        }
+
+        let update = update.iter().map(|x| x.to_string()).collect::<Vec<_>>();
+        update.join(",") + "\n"
+    });
+
+    rules.concat() + "\n" + &updates.collect::<String>()
+}
+
 fn main() {
+    let args = Args::from_env();
+    if args.run_generate(generate) {
+        return;
+    }
+
     let input = parse_input(&read_stdin());
 
     let (time, result) = timed(|| get_ordered_middles(&input).into_iter().sum::<i32>());
     println!("Part 1: {result} in {}μs", time.as_micros());
 
-    let (time, result) = timed(|| get_unordered_middles(&input).into_iter().sum::<i32>());
+    let (time, middles) = timed(|| get_unordered_middles(&input));
+    let result = middles.iter().flatten().sum::<i32>();
     println!("Part 2: {result} in {}μs", time.as_micros());
+
+    let cycles = middles.into_iter().filter_map(Result::err);
+    for cycle in cycles {
+        println!("Skipped an update whose rules form a cycle: {cycle:?}");
+    }
+
+    if args.flag("explain") {
+        for (i, update) in input.updates.iter().enumerate() {
+            let explanation = match explain(&input.requirements, update) {
+                Ok(explanation) if explanation.violations.is_empty() => continue,
+                Ok(explanation) => explanation,
+                Err(cycle) => {
+                    println!("Update {}: rules form a cycle {cycle:?}", i + 1);
+                    continue;
+                }
+            };
+
+            println!("Update {}: {update:?}", i + 1);
+            for v in &explanation.violations {
+                let (after, before) = v.positions;
+                println!(
+                    "  breaks {}|{}: {} at position {} is before {} at position {}",
+                    v.before,
+                    v.after,
+                    v.after,
+                    after + 1,
+                    v.before,
+                    before + 1
+                );
+            }
+            println!(
+                "  moving {:?} gives {:?}",
+                explanation.moves, explanation.fixed
+            );
+        }
+
+        println!("Most broken rules:");
+        for ((before, after), count) in violation_summary(&input).into_iter().take(10) {
+            println!("  {before}|{after}: {count} updates");
+        }
+    }
+
+    let limit = args.get::<usize>("enumerate");
+    if args.flag("orderings") || limit.is_some() {
+        let limit = limit.unwrap_or(0);
+        for (i, update) in input.updates.iter().enumerate() {
+            match count_orderings(&input.requirements, update) {
+                Ok(Some(count)) => println!("Update {}: {count} valid orderings", i + 1),
+                Ok(None) => {
+                    println!("Update {}: too many valid orderings to count", i + 1);
+                    continue;
+                }
+                Err(cycle) => {
+                    println!("Update {}: rules form a cycle {cycle:?}", i + 1);
+                    continue;
+                }
+            }
+
+            if limit == 0 {
+                continue;
+            }
+            for order in orderings(&input.requirements, update, limit)
+                .into_iter()
+                .flatten()
+            {
+                println!("  {order:?}");
+            }
+        }
+    }
 }
 
 // Part 1: 5713 in 993μs
@@ -125,7 +449,9 @@ fn main() {
 #[cfg(test)]
 mod tests {
     use crate::{
-        get_middle, get_ordered_middles, is_correctly_ordered, parse_input, reorder_incorrect,
+        count_orderings, explain, get_middle, get_ordered_middles, get_unordered_middles,
+        is_correctly_ordered, orderings, parse_input, reorder_incorrect, violation_summary,
+        violations, Violation,
     };
 
     #[test]
@@ -164,16 +490,125 @@ mod tests {
         let update = vec![75, 97, 47, 61, 53];
         let expected = vec![97, 75, 47, 61, 53];
 
-        assert_eq!(reorder_incorrect(&input.requirements, &update), expected);
+        assert_eq!(
+            reorder_incorrect(&input.requirements, &update),
+            Ok(expected)
+        );
 
         assert_eq!(
-            reorder_incorrect(&input.requirements, &vec![61, 13, 29]),
+            reorder_incorrect(&input.requirements, &vec![61, 13, 29]).unwrap(),
             [61, 29, 13]
         );
 
         assert_eq!(
-            reorder_incorrect(&input.requirements, &vec![97, 13, 75, 29, 47]),
+            reorder_incorrect(&input.requirements, &vec![97, 13, 75, 29, 47]).unwrap(),
             [97, 75, 47, 29, 13]
         );
+
+        // A repeated page is only placed once
+        assert_eq!(
+            reorder_incorrect(&input.requirements, &vec![13, 97, 13]).unwrap(),
+            [97, 13]
+        );
+    }
+
+    #[test]
+    fn all_orderings() {
+        let input = parse_input(include_str!("../example.txt"));
+        let middles = get_unordered_middles(&input);
+        assert_eq!(middles, [Ok(47), Ok(29), Ok(47)]);
+
+        // The example has a rule for every pair, so each order is unique
+        for update in &input.updates {
+            assert_eq!(count_orderings(&input.requirements, update), Ok(Some(1)));
+        }
+
+        let input = parse_input("1|3\n2|3\n3|4\n\n4,3,2,1\n5,4\n");
+        let update = &input.updates[0];
+        assert_eq!(count_orderings(&input.requirements, update), Ok(Some(2)));
+        assert_eq!(
+            orderings(&input.requirements, update, 5),
+            Ok(vec![vec![2, 1, 3, 4], vec![1, 2, 3, 4]])
+        );
+        assert_eq!(
+            count_orderings(&input.requirements, &input.updates[1]),
+            Ok(Some(2))
+        );
+    }
+
+    #[test]
+    fn cycles() {
+        let input = parse_input("1|2\n2|3\n3|1\n3|4\n\n4,3,2,1\n1,2\n");
+        let update = &input.updates[0];
+
+        assert_eq!(
+            reorder_incorrect(&input.requirements, update),
+            Err(vec![3, 1, 2])
+        );
+        assert_eq!(
+            count_orderings(&input.requirements, update),
+            Err(vec![3, 1, 2])
+        );
+        assert_eq!(get_unordered_middles(&input), [Err(vec![3, 1, 2])]);
+    }
+
+    #[test]
+    fn explanations() {
+        let input = parse_input(include_str!("../example.txt"));
+        let update = &input.updates[3];
+
+        assert_eq!(
+            violations(&input.requirements, update),
+            [Violation {
+                before: 97,
+                after: 75,
+                positions: (0, 1)
+            }]
+        );
+        let explanation = explain(&input.requirements, update).unwrap();
+        assert_eq!(explanation.moves.len(), 1);
+        assert_eq!(explanation.fixed, [97, 75, 47, 61, 53]);
+
+        let explanation = explain(&input.requirements, &input.updates[5]).unwrap();
+        assert_eq!(explanation.violations.len(), 4);
+        assert_eq!(explanation.moves.len(), 2);
+        assert_eq!(explanation.fixed, [97, 75, 47, 29, 13]);
+
+        let explanation = explain(&input.requirements, &input.updates[0]).unwrap();
+        assert_eq!(explanation.violations, []);
+        assert_eq!(explanation.moves, []);
+
+        let summary = violation_summary(&input);
+        assert_eq!(summary[0], ((29, 13), 2));
+        assert_eq!(summary.iter().map(|x| x.1).sum::<usize>(), 6);
+    }
+
+    #[test]
+    fn fewest_moves() {
+        // 3 is unrelated to the others, but 1 must come before 2 through it
+        let input = parse_input("1|3\n3|2\n\n2,3,1\n4,3,2,1\n");
+        let explanation = explain(&input.requirements, &input.updates[0]).unwrap();
+        assert_eq!(explanation.violations.len(), 2);
+        assert_eq!(explanation.moves.len(), 2);
+        assert_eq!(explanation.fixed, [1, 3, 2]);
+
+        // Only 1 is out of place, through the rule for 3
+        let explanation = explain(&input.requirements, &input.updates[1]).unwrap();
+        assert_eq!(explanation.moves, [1]);
+        assert_eq!(explanation.fixed, [4, 1, 3, 2]);
+
+        // More pages than fit in one word
+        let pages = (1..=64).map(|x| x.to_string()).collect::<Vec<_>>();
+        let input = parse_input(&format!("64|65\n\n65,{}\n", pages.join(",")));
+        let explanation = explain(&input.requirements, &input.updates[0]).unwrap();
+        assert_eq!(explanation.violations.len(), 1);
+        assert_eq!(explanation.moves, [65]);
+        assert_eq!(explanation.fixed, (1..=65).collect::<Vec<_>>());
+
+        let input = parse_input("1|2\n2|1\n\n2,1\n");
+        assert_eq!(
+            explain(&input.requirements, &input.updates[0]),
+            Err(vec![2, 1])
+        );
     }
 }
@@ -1,12 +1,16 @@
-use common::timed;
+use common::{math, timed, Args, Rng};
 
+/// Numbers are positive, so no operator makes the running total smaller and
+/// anything over the target can be dropped. So can totals that overflow
 fn can_make(target: i64, numbers: &[i64]) -> bool {
     fn do_loop(target: i64, numbers: &[i64], acc: i64) -> bool {
         match numbers {
             [] => acc == target,
+            _ if acc > target => false,
 
             [head, tail @ ..] => {
-                do_loop(target, tail, acc + head) || do_loop(target, tail, acc * head)
+                let next = |acc: Option<i64>| acc.is_some_and(|acc| do_loop(target, tail, acc));
+                next(acc.checked_add(*head)) || next(acc.checked_mul(*head))
             }
         }
     }
@@ -14,21 +18,23 @@ fn can_make(target: i64, numbers: &[i64]) -> bool {
     do_loop(target, &numbers[1..], numbers[0])
 }
 
-fn concat(a: i64, b: i64) -> i64 {
-    let b_places = (b as f64).log10().floor() + 1.0;
-    let a = a * (10_i64.pow(b_places as u32));
-    a + b
+/// `None` if the digits don't fit in an `i64`
+fn concat(a: i64, b: i64) -> Option<i64> {
+    math::checked_concat(a as u64, b as u64).and_then(|x| i64::try_from(x).ok())
 }
 
+/// As [`can_make`], with concatenation too
 fn can_make_concat(target: i64, numbers: &[i64]) -> bool {
     fn do_loop(target: i64, numbers: &[i64], acc: i64) -> bool {
         match numbers {
             [] => acc == target,
+            _ if acc > target => false,
 
             [head, tail @ ..] => {
-                do_loop(target, tail, acc + head)
-                    || do_loop(target, tail, acc * head)
-                    || do_loop(target, tail, concat(acc, *head))
+                let next = |acc: Option<i64>| acc.is_some_and(|acc| do_loop(target, tail, acc));
+                next(acc.checked_add(*head))
+                    || next(acc.checked_mul(*head))
+                    || next(concat(acc, *head))
             }
         }
     }
@@ -60,7 +66,41 @@ fn get_calibration_result(
         .sum()
 }
 
+fn generate(rng: &mut Rng, args: &Args) -> String {
+    let equations = args.get_or("equations", 850);
+
+    // Small enough that every combination of operators fits in an i64
+    (0..equations)
+        .map(|_| {
+            let numbers = (0..rng.range(2..7))
+                .map(|_| rng.range(1..100))
+                .collect::<Vec<_>>();
+
+            let mut target = numbers[0];
+            for x in &numbers[1..] {
+                target = match rng.below(3) {
+                    0 => target + x,
+                    1 => target * x,
+                    _ => concat(target, *x).expect("small numbers fit"),
+                };
+            }
+
+            if rng.chance(0.3) {
+                target += rng.range(1..10);
+            }
+
+            let numbers = numbers.iter().map(|x| x.to_string()).collect::<Vec<_>>();
+            format!("{target}: {}\n", numbers.join(" "))
+        })
+        .collect()
+}
+
 fn main() {
+    let args = Args::from_env();
+    if args.run_generate(generate) {
+        return;
+    }
+
     let input = parse_input(&common::read_stdin());
 
     let (time, calibration_result) = timed(|| get_calibration_result(input.clone(), can_make));
@@ -93,8 +133,17 @@ mod tests {
 
     #[test]
     fn concat_tests() {
-        assert_eq!(concat(15, 3), 153);
-        assert_eq!(concat(1, 300), 1300);
+        assert_eq!(concat(15, 3), Some(153));
+        assert_eq!(concat(1, 300), Some(1300));
+        assert_eq!(concat(1, 9999999999999999), Some(19999999999999999));
+        assert_eq!(concat(922337203685477580, 7), Some(i64::MAX));
+        assert_eq!(concat(922337203685477580, 8), None);
+        assert_eq!(concat(i64::MAX, 1), None);
+
+        // Branches that overflow can't reach the target
+        assert!(can_make_concat(i64::MAX, &[922337203685477580, 7]));
+        assert!(!can_make_concat(i64::MAX, &[922337203685477580, 8, 1]));
+        assert!(!can_make(i64::MAX, &[i64::MAX / 2 + 1, 2, 1]));
     }
 
     #[test]
@@ -1,4 +1,4 @@
-use common::timed;
+use common::{math, timed, Args, Rng};
 
 use std::{
     collections::{HashMap, HashSet},
@@ -82,25 +82,24 @@ fn create_antinodes_extended(grid: Grid) -> Grid {
     for (_, positions) in grid.nodes.iter() {
         for pos in positions {
             let other_positions = positions.iter().filter(|p| *p != pos).map(|other| {
-                let vector = Position::new(other.x - pos.x, other.y - pos.y);
+                let (dx, dy) = (other.x - pos.x, other.y - pos.y);
+                // Reduce so every grid point on the line is hit, not just
+                // multiples of the full antenna distance
+                let divisor = math::gcd(dx as i64, dy as i64) as isize;
+                let vector = Position::new(dx / divisor, dy / divisor);
 
                 (*other, vector)
             });
 
-            other_positions.for_each(|(other_pos, vec)| {
-                // Add antinode to (other_pos + vec) and (current_pos - vec)
-                // Plus on antenna itself
-                // Repeat while inside grid
-                let mut node_pos = other_pos;
-                while grid.is_inside(&node_pos) {
-                    antinodes.insert(node_pos);
-                    node_pos = node_pos + vec;
-                }
-
-                node_pos = *pos;
-                while grid.is_inside(&node_pos) {
-                    antinodes.insert(node_pos);
-                    node_pos = node_pos - vec;
+            other_positions.for_each(|(_, vec)| {
+                // Every point on the line through both antennas, walking
+                // both ways from this one so the points between them count
+                for step in [vec, Position::new(-vec.x, -vec.y)] {
+                    let mut node_pos = *pos;
+                    while grid.is_inside(&node_pos) {
+                        antinodes.insert(node_pos);
+                        node_pos = node_pos + step;
+                    }
                 }
             });
         }
@@ -149,7 +148,31 @@ fn create_grid(input: &str) -> Grid {
     }
 }
 
+fn generate(rng: &mut Rng, args: &Args) -> String {
+    let size = args.get_or("size", 50);
+    let antennas = args.get_or("antennas", 200);
+    let frequencies = ('a'..='z')
+        .chain('A'..='Z')
+        .chain('0'..='9')
+        .take(args.get_or("frequencies", 62))
+        .collect::<Vec<_>>();
+
+    let mut grid = vec![vec!['.'; size]; size];
+    for _ in 0..antennas {
+        grid[rng.index(size)][rng.index(size)] = *rng.choose(&frequencies);
+    }
+
+    grid.into_iter()
+        .map(|row| row.into_iter().chain(['\n']).collect::<String>())
+        .collect()
+}
+
 fn main() {
+    let args = Args::from_env();
+    if args.run_generate(generate) {
+        return;
+    }
+
     let grid = create_grid(&common::read_stdin());
 
     let (time, with_antinodes) = timed(|| create_antinodes(grid.clone()));
@@ -218,4 +241,11 @@ mod tests {
         let grid = create_antinodes_extended(grid);
         assert_eq!(grid.antinodes.len(), 34);
     }
+
+    #[test]
+    fn extended_reduced_step_test() {
+        let grid = create_grid("a....\n.....\n..a..\n.....\n.....");
+        let grid = create_antinodes_extended(grid);
+        assert_eq!(grid.antinodes.len(), 5);
+    }
 }
@@ -1,7 +1,11 @@
-use common::{timed, iter::PairsIter};
+use common::{
+    iter::PairsIter,
+    ranges::{Interval, IntervalSet},
+    timed, Args, Input, Rng,
+};
 use std::{
     fmt::{Display, Write},
-    iter::{from_fn, repeat_n},
+    iter::repeat_n,
 };
 
 #[derive(Debug, Clone)]
@@ -19,10 +23,12 @@ impl Display for ExpandedDiskMap {
 }
 
 impl ExpandedDiskMap {
-    fn new(input: &str) -> Self {
+    fn new(input: impl AsRef<[u8]>) -> Self {
         let pairs = input
-            .chars()
-            .filter_map(|x| x.to_digit(10).map(|x| x as usize))
+            .as_ref()
+            .iter()
+            .filter(|x| x.is_ascii_digit())
+            .map(|x| (x - b'0') as usize)
             .pairs();
 
         let mut map = vec![];
@@ -64,82 +70,35 @@ impl ExpandedDiskMap {
     }
 
     fn shrink_whole_files(mut self) -> Self {
-        #[derive(Debug)]
-        struct Window {
-            start: usize,
-            end: usize,
-        }
-        impl Window {
-            fn len(&self) -> usize {
-                self.end - self.start + 1
-            }
-        }
-
-        // get indices of contiguous similar values
-        fn get_window(ExpandedDiskMap(data): &ExpandedDiskMap, i: usize) -> Window {
-            let value = data[i];
-
-            let mut start = i as isize;
-            let mut end = i as isize;
-
-            while start > 0 && data[(start - 1) as usize] == value {
-                start -= 1;
-            }
-
-            while end < data.len() as isize - 1 && data[(end + 1) as usize] == value {
-                end += 1;
-            }
-
-            Window {
-                start: start as usize,
-                end: end as usize,
-            }
-        }
-
-        fn get_similar(data: &ExpandedDiskMap) -> Vec<Window> {
-            let mut index = 0;
-            let iter = from_fn(|| {
-                if index >= data.0.len() {
-                    return None;
-                }
-                let window = get_window(data, index);
-                index = window.end + 1;
+        let mut files = vec![];
+        let mut free = IntervalSet::new();
 
-                Some(window)
-            });
-
-            iter.filter(|x| data.0[x.start].is_some()).collect()
-        }
-
-        fn leftmost_empty_of_size_n(data: &ExpandedDiskMap, size: usize) -> Option<Window> {
-            for (i, x) in data.0.iter().enumerate() {
+        let mut start = 0;
+        for (i, x) in self.0.iter().enumerate() {
+            if i + 1 == self.0.len() || self.0[i + 1] != *x {
+                let span = Interval::new(start as i64, i as i64 + 1);
                 match x {
-                    Some(_) => continue,
-                    None => {
-                        let window = get_window(data, i);
-
-                        if window.len() >= size {
-                            return Some(window);
-                        }
-                    }
+                    Some(id) => files.push((*id, span)),
+                    None => free.insert(span),
                 }
+                start = i + 1;
             }
-
-            None
         }
 
-        let similar = get_similar(&self);
+        for (id, file) in files.into_iter().rev() {
+            let Some(empty) = free.first_fit(file.len()) else {
+                continue;
+            };
+            if file.start <= empty.start {
+                continue;
+            }
 
-        for s in similar.iter().rev() {
-            if let Some(empty) = leftmost_empty_of_size_n(&self, s.len()) {
-                if s.start <= empty.start {
-                    continue;
-                }
+            let target = Interval::with_len(empty.start, file.len());
+            self.0[target.start as usize..target.end as usize].fill(Some(id));
+            self.0[file.start as usize..file.end as usize].fill(None);
 
-                for i in 0..s.len() {
-                    self.0.swap(s.start + i, empty.start + i);
-                }
-            }
+            free.remove(target);
+            free.insert(file);
         }
 
         self
@@ -155,10 +114,26 @@ impl ExpandedDiskMap {
     }
 }
 
+fn generate(rng: &mut Rng, args: &Args) -> String {
+    let length = args.get_or("length", 19999usize);
+
+    (0..length)
+        .map(|i| {
+            // Files are at least one block long, gaps can be empty
+            let min = if i.is_multiple_of(2) { 1 } else { 0 };
+            char::from(b'0' + rng.range(min..10) as u8)
+        })
+        .chain(['\n'])
+        .collect()
+}
+
 fn main() {
-    let input = common::read_stdin();
+    let args = Args::from_env();
+    if args.run_generate(generate) {
+        return;
+    }
 
-    let map = ExpandedDiskMap::new(&input);
+    let map = ExpandedDiskMap::new(&*Input::stdin().bytes());
     let (time, checksum) = timed(|| map.clone().shrink().checksum());
     println!("Part 1: {checksum} in {}μs", time.as_micros());
 